@@ -11,25 +11,51 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+pub mod migrations;
+
 pub use pallet::*;
-use sp_std::vec::Vec;
+use frame_support::{
+	sp_runtime::{
+		app_crypto::sp_core::H256,
+		helpers_128bit::multiply_by_rational_with_rounding,
+		traits::{Saturating, UniqueSaturatedInto, Zero},
+		Perbill, Rounding,
+	},
+	traits::{
+		BalanceStatus, ConstU32, Currency, Get, Imbalance, Randomness, ReservableCurrency,
+	},
+	weights::Weight,
+	BoundedVec,
+};
+use sp_std::{cmp::Reverse, vec::Vec};
 pub type BlockNumber = u64;
 use codec::Encode;
 
 #[frame_support::pallet]
 pub mod pallet {
 	use frame_support::{dispatch::DispatchResult, pallet_prelude::*};
-	use frame_support::{sp_runtime::app_crypto::sp_core::H256, traits::Randomness};
+	use frame_support::sp_runtime::app_crypto::sp_core::H256;
 	use frame_system::pallet_prelude::*;
 
 	// important to use outside structs and consts
 	use super::*;
 
+	/// The balance type of the currency games are staked in.
+	pub type BalanceOf<T> =
+		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+	/// Funds taken out of accounts in the currency games are staked in.
+	pub type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+		<T as frame_system::Config>::AccountId,
+	>>::NegativeImbalance;
+
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	/// define a pallet struct
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
-	// disable this so that Vec can be used.
-	#[pallet::without_storage_info]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// Configure the pallet by specifying the parameters and types on which it depends.
@@ -48,6 +74,97 @@ pub mod pallet {
 		/// implements the trait. When installing this pallet in a runtime, you
 		/// must make sure to give it a randomness source that suits its needs.
 		type RandomnessSource: Randomness<H256, BlockNumber>;
+		/// Number of blocks a player has to make their move before they forfeit the game.
+		#[pallet::constant]
+		type MoveTimeout: Get<BlockNumber>;
+		/// Number of blocks an open challenge stays in the lobby before it expires.
+		#[pallet::constant]
+		type OpenChallengeExpiry: Get<BlockNumber>;
+		/// The largest number of rows a board can be created with.
+		#[pallet::constant]
+		type MaxRows: Get<u32>;
+		/// The largest number of columns a board can be created with.
+		#[pallet::constant]
+		type MaxColumns: Get<u32>;
+		/// The Elo rating given to accounts that haven't played yet.
+		#[pallet::constant]
+		type InitialRating: Get<u32>;
+		/// The Elo K-factor, the most rating points that can change hands in a game.
+		#[pallet::constant]
+		type RatingKFactor: Get<u32>;
+		/// The most challenges and games two accounts can have going on at the same time.
+		#[pallet::constant]
+		type MaxGamesPerPair: Get<u32>;
+		/// The most moves recorded for a game. A game that reaches it is drawn, which can
+		/// only happen in PopOut games, since it must be at least `MaxRows * MaxColumns`.
+		#[pallet::constant]
+		type MaxMoves: Get<u32>;
+		/// Whether players can take back moves in rated games. They always can in unrated
+		/// games.
+		#[pallet::constant]
+		type TakebacksInRatedGames: Get<bool>;
+		/// The currency players stake on games.
+		type Currency: ReservableCurrency<Self::AccountId>;
+		/// The part of the pot of a staked game taken as a fee, when the game is won or
+		/// drawn.
+		#[pallet::constant]
+		type HouseFee: Get<Perbill>;
+		/// The account house fees are paid to. It must exist, holding at least the
+		/// existential deposit: fees that can't be paid to it are left with the players.
+		#[pallet::constant]
+		type HouseAccount: Get<Self::AccountId>;
+		/// The most players a tournament, or a Swiss tournament, can have.
+		#[pallet::constant]
+		type MaxTournamentPlayers: Get<u32>;
+		/// The most players a league can have.
+		#[pallet::constant]
+		type MaxLeaguePlayers: Get<u32>;
+		/// Number of blocks after its start at which the season of a league closes.
+		#[pallet::constant]
+		type LeagueSeasonLength: Get<BlockNumber>;
+		/// Number of moves after which no more bets can be placed on a game.
+		#[pallet::constant]
+		type BettingCutoff: Get<u32>;
+		/// The most bets that can be placed on a game.
+		#[pallet::constant]
+		type MaxBetsPerGame: Get<u32>;
+		/// The smallest amount that can be bet.
+		#[pallet::constant]
+		type MinimumBet: Get<BalanceOf<Self>>;
+		/// Number of blocks after it ends at which a finished board can be removed.
+		#[pallet::constant]
+		type PruneAfter: Get<BlockNumber>;
+		/// The deposit reserved from the challenger for every board, paid back when the board
+		/// is removed.
+		#[pallet::constant]
+		type GameDeposit: Get<BalanceOf<Self>>;
+		/// The most boards or leagues queued for the same block, in each of the queues of
+		/// move deadlines, lobby expiries, league ends and boards to prune. Entries beyond it
+		/// are queued for the next block with room.
+		#[pallet::constant]
+		type MaxQueuedPerBlock: Get<u32>;
+	}
+
+	/// The most slots a board can have, the width of its bitboards.
+	pub const MAX_BOARD_SLOTS: u32 = 128;
+	/// The most columns a board can have: with at least 3 rows and an empty bit on top of
+	/// every column, a column takes at least 4 slots.
+	pub const MAX_BOARD_COLUMNS: u32 = MAX_BOARD_SLOTS / 4;
+	/// The longest line a board can have to win: `win_length` fits in both the rows and the
+	/// columns, and an 11 x 11 board doesn't fit in its bitboards.
+	pub const MAX_WIN_LENGTH: u32 = 10;
+
+	/// A queue of boards, or leagues, for a block.
+	pub type BlockQueue<T> = BoundedVec<H256, <T as Config>::MaxQueuedPerBlock>;
+
+	/// The most fixtures a league can have: every player plays every other player twice.
+	pub struct MaxLeagueFixtures<T>(PhantomData<T>);
+
+	impl<T: Config> Get<u32> for MaxLeagueFixtures<T> {
+		fn get() -> u32 {
+			let players = T::MaxLeaguePlayers::get();
+			players.saturating_mul(players.saturating_sub(1))
+		}
 	}
 
 	/// An enum that indicates the state of a single slot on a ConnectFourBoard.
@@ -60,14 +177,34 @@ pub mod pallet {
 	}
 
 	/// keep track of player in other spaces.
-	#[derive(Encode, Decode, Clone, Default, RuntimeDebug, TypeInfo, PartialEq, Copy)]
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Default,
+		RuntimeDebug,
+		TypeInfo,
+		PartialEq,
+		Copy,
+		MaxEncodedLen,
+	)]
 	pub enum Player {
 		#[default]
 		One,
 		Two,
 	}
 
-	#[derive(Encode, Decode, Clone, Default, RuntimeDebug, TypeInfo, PartialEq, Copy)]
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Default,
+		RuntimeDebug,
+		TypeInfo,
+		PartialEq,
+		Copy,
+		MaxEncodedLen,
+	)]
 	pub enum WinState {
 		Player(Player),
 		Draw,
@@ -75,8 +212,19 @@ pub mod pallet {
 		Ongoing,
 	}
 
+	impl WinState {
+		/// the same result, with the players swapped.
+		pub fn swapped(self) -> Self {
+			match self {
+				WinState::Player(Player::One) => WinState::Player(Player::Two),
+				WinState::Player(Player::Two) => WinState::Player(Player::One),
+				other => other,
+			}
+		}
+	}
+
 	/// scorecard for accounts.
-	#[derive(Encode, Decode, Clone, Default, RuntimeDebug, TypeInfo)]
+	#[derive(Encode, Decode, Clone, Default, RuntimeDebug, TypeInfo, MaxEncodedLen)]
 	pub struct ScoreCard {
 		pub played: u64,
 		pub won: u64,
@@ -86,20 +234,539 @@ pub mod pallet {
 		pub points: i64,
 	}
 
-	/// A connect four board struct that contains a state matrix.
+	/// The rule set a game is played with.
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		Default,
+		RuntimeDebug,
+		TypeInfo,
+		PartialEq,
+		MaxEncodedLen,
+	)]
+	pub enum GameVariant {
+		/// Coins can only be dropped into columns.
+		#[default]
+		Standard,
+		/// On their turn, a player can pop one of their own coins out of the bottom of a
+		/// column instead of dropping one.
+		PopOut,
+	}
+
+	/// A chess clock time control, in blocks.
+	#[derive(Encode, Decode, Clone, Copy, RuntimeDebug, TypeInfo, PartialEq, MaxEncodedLen)]
+	pub struct TimeControl {
+		/// the time each player has for all of their moves.
+		pub bank: BlockNumber,
+		/// the time added to a player's clock after each of their moves.
+		pub increment: BlockNumber,
+	}
+
+	/// Who moves first in a game.
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		Default,
+		RuntimeDebug,
+		TypeInfo,
+		PartialEq,
+		MaxEncodedLen,
+	)]
+	pub enum FirstMove {
+		/// The challenger, player 1.
+		#[default]
+		Challenger,
+		/// The opponent, player 2.
+		Opponent,
+		/// Either player, drawn with the `RandomnessSource` when the game starts. The draw is
+		/// only as fair as that source: with a predictable one, such as the collective flip,
+		/// whoever starts the game can work out the draw and time their call to pick who
+		/// moves first. Use it where that doesn't matter, such as casual games.
+		Random,
+	}
+
+	/// The rules a game is played with, chosen by the challenger.
+	#[derive(Encode, Decode, Clone, Copy, RuntimeDebug, TypeInfo, PartialEq, MaxEncodedLen)]
+	pub struct GameSettings {
+		pub rows: u8,
+		pub columns: u8,
+		/// number of consecutive coins needed to win.
+		pub win_length: u8,
+		pub variant: GameVariant,
+		/// whether the result of the game changes the players' Elo ratings.
+		pub rated: bool,
+		/// the chess clock the game is played on. Without one, each move must be made
+		/// within `MoveTimeout` blocks.
+		pub time_control: Option<TimeControl>,
+		/// who moves first.
+		pub first_move: FirstMove,
+		/// whether the second player can take the opening coin as their own and swap sides,
+		/// instead of making their first move.
+		pub swap_rule: bool,
+	}
+
+	impl Default for GameSettings {
+		/// The classic 6 rows x 7 columns connect four, rated, without a clock or the swap
+		/// rule, where the challenger moves first.
+		fn default() -> Self {
+			GameSettings {
+				rows: 6,
+				columns: 7,
+				win_length: 4,
+				variant: GameVariant::Standard,
+				rated: true,
+				time_control: None,
+				first_move: FirstMove::Challenger,
+				swap_rule: false,
+			}
+		}
+	}
+
+	impl GameSettings {
+		/// checks that the board fits within the configured bounds, that a line of
+		/// `win_length` (from 3 to `MAX_WIN_LENGTH`) fits both horizontally and vertically,
+		/// and that a clock starts with some time on it.
+		pub fn is_valid(&self, max_rows: u32, max_columns: u32) -> bool {
+			self.win_length >= 3 &&
+				self.win_length as u32 <= MAX_WIN_LENGTH &&
+				self.rows >= self.win_length &&
+				self.columns >= self.win_length &&
+				self.rows as u32 <= max_rows &&
+				self.columns as u32 <= max_columns &&
+				self.time_control.map_or(true, |time_control| time_control.bank > 0)
+		}
+
+		/// checks that the settings leave it to the pairing to decide who moves first: the
+		/// first player of a pairing moves first, without the swap rule.
+		pub fn suits_competitions(&self) -> bool {
+			self.first_move == FirstMove::Challenger && !self.swap_rule
+		}
+	}
+
+	/// A challenge listed in the lobby, that any account can join.
+	#[derive(Encode, Decode, Clone, Default, RuntimeDebug, TypeInfo, PartialEq, MaxEncodedLen)]
+	pub struct OpenChallenge<AccountId> {
+		/// This is the challenger, who will be player 1.
+		pub creator: AccountId,
+		pub settings: GameSettings,
+		/// block at which the challenge is removed from the lobby.
+		pub expires_at: BlockNumber,
+	}
+
+	/// The two kinds of move a player can make.
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		Default,
+		RuntimeDebug,
+		TypeInfo,
+		PartialEq,
+		MaxEncodedLen,
+	)]
+	pub enum MoveKind {
+		/// a coin dropped into a column.
+		#[default]
+		Drop,
+		/// a coin popped out of the bottom of a column, in PopOut games.
+		Pop,
+	}
+
+	/// A move in the history of a game.
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		Default,
+		RuntimeDebug,
+		TypeInfo,
+		PartialEq,
+		MaxEncodedLen,
+	)]
+	pub struct Move {
+		pub player: Player,
+		pub column: u8,
+		/// block in which the move was made.
+		pub block: BlockNumber,
+		pub kind: MoveKind,
+		/// the players' clocks before the move, in games with a time control, restored when
+		/// the move is taken back.
+		pub clock: Option<Clock>,
+	}
+
+	/// A board position: a bitboard of the coins of each player, and the height of every
+	/// column. Slot (row, column) is bit `column * (rows + 1) + row`, so that every column
+	/// has an empty bit on top, which keeps lines from wrapping into the next column.
+	/// NOTE: `u128` rather than `u64`, so that boards up to 8 x 9 (`MaxRows` x `MaxColumns`)
+	/// fit with their extra row.
+	#[derive(Encode, Decode, Clone, Default, RuntimeDebug, TypeInfo, PartialEq, MaxEncodedLen)]
+	pub struct Bitboards {
+		pub player_1: u128,
+		pub player_2: u128,
+		/// number of coins in every column.
+		pub heights: BoundedVec<u8, ConstU32<MAX_BOARD_COLUMNS>>,
+	}
+
+	/// The direction of a line of coins, from its first coin to its last.
+	#[derive(Encode, Decode, Clone, Copy, RuntimeDebug, TypeInfo, PartialEq, MaxEncodedLen)]
+	pub enum Direction {
+		/// along a row, to the right.
+		Horizontal,
+		/// up a column.
+		Vertical,
+		/// up and to the right.
+		Diagonal,
+		/// up and to the left.
+		AntiDiagonal,
+	}
+
+	impl Direction {
+		/// the (row, column) step from one coin of a line to the next.
+		pub fn step(&self) -> (isize, isize) {
+			match self {
+				Direction::Horizontal => (0, 1),
+				Direction::Vertical => (1, 0),
+				Direction::Diagonal => (1, 1),
+				Direction::AntiDiagonal => (1, -1),
+			}
+		}
+	}
+
+	/// The line of coins that won a game.
+	#[derive(Encode, Decode, Clone, RuntimeDebug, TypeInfo, PartialEq, MaxEncodedLen)]
+	pub struct WinningLine {
+		pub direction: Direction,
+		/// (row, column) of the coins, in the line's direction.
+		pub cells: BoundedVec<(u8, u8), ConstU32<MAX_WIN_LENGTH>>,
+	}
+
+	/// the directions lines are checked in.
+	const DIRECTIONS: [Direction; 4] =
+		[Direction::Horizontal, Direction::Vertical, Direction::Diagonal, Direction::AntiDiagonal];
+
+	/// the bit of slot (row, column) on a board with `rows` rows.
+	fn slot(rows: usize, row: usize, column: usize) -> u128 {
+		1u128 << (column * (rows + 1) + row)
+	}
+
+	/// the bits of every slot of a column, on a board with `rows` rows.
+	fn column_mask(rows: usize, column: usize) -> u128 {
+		((1u128 << rows) - 1) << (column * (rows + 1))
+	}
+
+	/// the slots of `coins` that start a line of `length` coins going in the (row, column)
+	/// `step`, on a board with `rows` rows.
+	fn line_starts(coins: u128, rows: usize, step: (isize, isize), length: isize) -> u128 {
+		// distance in bits between two consecutive slots of the line.
+		let delta = step.1 * (rows as isize + 1) + step.0;
+		let mut starts = coins;
+		for k in 1..length {
+			let shift = (k * delta).unsigned_abs() as u32;
+			starts &= if delta > 0 { coins >> shift } else { coins << shift };
+		}
+		starts
+	}
+
+	impl Bitboards {
+		/// an empty board with `columns` columns.
+		pub fn new(columns: u8) -> Self {
+			let heights = BoundedVec::truncate_from(sp_std::vec![0; columns as usize]);
+			Bitboards { player_1: 0, player_2: 0, heights }
+		}
+
+		/// the position of a grid of coins, bottom row first.
+		pub fn from_grid(grid: &[Vec<Coin>]) -> Self {
+			let rows = grid.len();
+			let columns = grid.first().map_or(0, |row| row.len());
+			let mut bitboards = Self::new(columns as u8);
+			for (row, coins) in grid.iter().enumerate() {
+				for (column, coin) in coins.iter().enumerate() {
+					match coin {
+						Coin::Player1 => bitboards.player_1 |= slot(rows, row, column),
+						Coin::Player2 => bitboards.player_2 |= slot(rows, row, column),
+						Coin::Empty => continue,
+					}
+					bitboards.heights[column] = row as u8 + 1;
+				}
+			}
+			bitboards
+		}
+
+		/// the coin in slot (row, column), on a board with `rows` rows.
+		pub fn coin_at(&self, rows: usize, row: usize, column: usize) -> Coin {
+			let bit = slot(rows, row, column);
+			if self.player_1 & bit != 0 {
+				Coin::Player1
+			} else if self.player_2 & bit != 0 {
+				Coin::Player2
+			} else {
+				Coin::Empty
+			}
+		}
+
+		/// the grid of coins on a board with `rows` rows, bottom row first.
+		pub fn to_grid(&self, rows: usize) -> Vec<Vec<Coin>> {
+			(0..rows)
+				.map(|row| {
+					(0..self.heights.len()).map(|column| self.coin_at(rows, row, column)).collect()
+				})
+				.collect()
+		}
+	}
+
+	/// The time left to each player of a game with a time control, in blocks, as of the
+	/// last move.
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		Default,
+		RuntimeDebug,
+		TypeInfo,
+		PartialEq,
+		MaxEncodedLen,
+	)]
+	pub struct Clock {
+		pub player_1: BlockNumber,
+		pub player_2: BlockNumber,
+	}
+
+	impl Clock {
+		/// the time left to `player`.
+		pub fn time_left(&self, player: Player) -> BlockNumber {
+			match player {
+				Player::One => self.player_1,
+				Player::Two => self.player_2,
+			}
+		}
+
+		/// takes the `spent` blocks of a move off the clock of `player`, and adds the
+		/// `increment`.
+		pub fn charge(&mut self, player: Player, spent: BlockNumber, increment: BlockNumber) {
+			let time_left = match player {
+				Player::One => &mut self.player_1,
+				Player::Two => &mut self.player_2,
+			};
+			*time_left = time_left.saturating_sub(spent).saturating_add(increment);
+		}
+	}
+
+	/// A connect four board struct that contains the board position.
 	/// NOTE: This will inturn be stored in the ConnectFourBoards StorageMap
-	#[derive(Encode, Decode, Clone, Default, RuntimeDebug, TypeInfo)]
+	#[derive(Encode, Decode, Clone, Default, RuntimeDebug, TypeInfo, MaxEncodedLen)]
 	pub struct ConnectFourBoardStruct<AccountId> {
-		// TODO: perhaps a map can be used instead of a vec?
-		pub state: Option<Vec<Vec<Coin>>>,
+		/// the position, kept after the game ends to show the final position.
+		pub state: Option<Bitboards>,
 		/// This is the challenger
 		pub player_1: AccountId,
 		pub player_2: AccountId,
 		pub challenge_accepted: bool,
-		id: H256,
+		pub(crate) id: H256,
 		pub active: bool,
-		last_played: Option<AccountId>,
+		pub(crate) last_played: Option<AccountId>,
 		pub win_state: WinState,
+		/// block at which the last move was made, the game started, or the game ended.
+		pub last_move_at: BlockNumber,
+		/// dimensions and rules chosen for this game.
+		pub settings: GameSettings,
+		/// the coins that won the game, if it was won on the board.
+		pub winning_line: Option<WinningLine>,
+		/// the player who offered a draw, until the opponent answers or a move is made.
+		pub draw_offer: Option<Player>,
+		/// the player who asked to take back their last move, until the opponent accepts
+		/// or a move is made.
+		pub takeback_request: Option<Player>,
+		/// the players' clocks, in games with a time control.
+		pub clock: Option<Clock>,
+		/// the player who made, or will make, the first move.
+		pub first_player: Player,
+		/// whether the players have swapped sides, under the swap rule.
+		pub swapped: bool,
+		/// the number of moves made on the board, counting the ones taken back since.
+		pub moves_played: u32,
+	}
+
+	/// The stages a tournament or a league goes through.
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		Default,
+		RuntimeDebug,
+		TypeInfo,
+		PartialEq,
+		MaxEncodedLen,
+	)]
+	pub enum TournamentStatus {
+		/// Players can register.
+		#[default]
+		Registration,
+		/// The games are being played.
+		Running,
+		/// The final has been won, or the season has closed.
+		Finished,
+	}
+
+	/// A match of a tournament round, whose winner goes through to the next round.
+	#[derive(Encode, Decode, Clone, RuntimeDebug, TypeInfo, PartialEq, MaxEncodedLen)]
+	pub struct TournamentMatch<AccountId> {
+		/// the higher seed, who moves first.
+		pub player_1: AccountId,
+		/// `None` is a bye, `player_1` goes through without playing.
+		pub player_2: Option<AccountId>,
+		/// the game being played, replaced by a new one when a game is drawn.
+		pub board_id: Option<H256>,
+		pub winner: Option<AccountId>,
+	}
+
+	/// A single-elimination tournament.
+	#[derive(
+		Encode,
+		Decode,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+		TypeInfo,
+		MaxEncodedLen,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct Tournament<T: Config> {
+		pub creator: T::AccountId,
+		/// the rules every game of the tournament is played with.
+		pub settings: GameSettings,
+		/// the most players that can register.
+		pub max_players: u32,
+		/// the registered players, seeded by rating when the tournament starts.
+		pub players: BoundedVec<T::AccountId, T::MaxTournamentPlayers>,
+		pub status: TournamentStatus,
+		/// the current round, starting at 1.
+		pub round: u32,
+		/// the matches of the current round, in bracket order.
+		pub matches: BoundedVec<TournamentMatch<T::AccountId>, T::MaxTournamentPlayers>,
+	}
+
+	/// A game of a league season.
+	#[derive(Encode, Decode, Clone, RuntimeDebug, TypeInfo, PartialEq, MaxEncodedLen)]
+	pub struct LeagueFixture<AccountId> {
+		/// the home player, who moves first.
+		pub home: AccountId,
+		pub away: AccountId,
+		pub board_id: H256,
+		/// the result of the game, with the home player as `Player::One`.
+		pub result: WinState,
+	}
+
+	/// A round-robin league, where every player plays every other player at home and away.
+	#[derive(
+		Encode,
+		Decode,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+		TypeInfo,
+		MaxEncodedLen,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct League<T: Config> {
+		pub creator: T::AccountId,
+		/// the rules every game of the league is played with.
+		pub settings: GameSettings,
+		/// the most players that can register.
+		pub max_players: u32,
+		pub players: BoundedVec<T::AccountId, T::MaxLeaguePlayers>,
+		pub status: TournamentStatus,
+		/// block at which the season closes, set when it starts.
+		pub ends_at: BlockNumber,
+		/// the current round, starting at 1, see `league_round`.
+		pub round: u32,
+		/// the games of the rounds started so far, in round order.
+		pub fixtures: BoundedVec<LeagueFixture<T::AccountId>, MaxLeagueFixtures<T>>,
+		/// the final standings, best first, once the season has closed.
+		pub ranking: BoundedVec<T::AccountId, T::MaxLeaguePlayers>,
+	}
+
+	/// A player of a Swiss tournament.
+	#[derive(
+		Encode,
+		Decode,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+		TypeInfo,
+		MaxEncodedLen,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct SwissPlayer<T: Config> {
+		pub account: T::AccountId,
+		/// 2 points for a win or a bye, and 1 for a draw.
+		pub score: u32,
+		/// the players met so far, in round order.
+		pub opponents: BoundedVec<T::AccountId, T::MaxTournamentPlayers>,
+		/// the number of games played moving first, minus those played moving second.
+		pub colour_balance: i32,
+		pub had_bye: bool,
+	}
+
+	/// A bet placed on the result of a game by an account that doesn't play in it.
+	#[derive(Encode, Decode, Clone, RuntimeDebug, TypeInfo, PartialEq, MaxEncodedLen)]
+	pub struct Bet<AccountId, Balance> {
+		pub bettor: AccountId,
+		/// a win for either player, or a draw.
+		pub outcome: WinState,
+		pub amount: Balance,
+	}
+
+	/// A game of the current round of a Swiss tournament.
+	#[derive(Encode, Decode, Clone, RuntimeDebug, TypeInfo, PartialEq, MaxEncodedLen)]
+	pub struct SwissGame<AccountId> {
+		/// the player who moves first.
+		pub player_1: AccountId,
+		pub player_2: AccountId,
+		pub board_id: H256,
+		pub result: WinState,
+	}
+
+	/// A Swiss tournament: a fixed number of rounds, where each round pairs players with
+	/// the same or close scores who haven't met yet.
+	#[derive(
+		Encode,
+		Decode,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+		TypeInfo,
+		MaxEncodedLen,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct SwissTournament<T: Config> {
+		pub creator: T::AccountId,
+		/// the rules every game of the tournament is played with.
+		pub settings: GameSettings,
+		/// the most players that can register.
+		pub max_players: u32,
+		/// the number of rounds played.
+		pub rounds: u32,
+		/// the registered players, seeded by rating when the tournament starts.
+		pub players: BoundedVec<SwissPlayer<T>, T::MaxTournamentPlayers>,
+		pub status: TournamentStatus,
+		/// the current round, starting at 1.
+		pub round: u32,
+		/// the games of the current round.
+		pub games: BoundedVec<SwissGame<T::AccountId>, T::MaxTournamentPlayers>,
+		/// the final standings, best first, once the last round has been played.
+		pub ranking: BoundedVec<T::AccountId, T::MaxTournamentPlayers>,
 	}
 
 	#[derive(Encode, Decode, Clone, Default, RuntimeDebug, TypeInfo)]
@@ -112,12 +779,18 @@ pub mod pallet {
 		ColumnFull,
 		BoardNotReady,
 		GameEnded,
+		PopNotAllowed,
+		NotYourCoin,
 	}
 
 	impl<AccountId: core::cmp::PartialEq + Clone> ConnectFourBoardStruct<AccountId> {
 		/// Constructor for the ConnectFourBoardStruct
-		fn new(board_id: H256, player_1: AccountId, player_2: AccountId) -> Self {
-			// TODO: randomly create a board ID?
+		fn new(
+			board_id: H256,
+			player_1: AccountId,
+			player_2: AccountId,
+			settings: GameSettings,
+		) -> Self {
 			ConnectFourBoardStruct {
 				state: None,
 				challenge_accepted: false,
@@ -127,28 +800,40 @@ pub mod pallet {
 				player_2,
 				last_played: None,
 				win_state: WinState::Ongoing,
+				last_move_at: 0,
+				settings,
+				winning_line: None,
+				draw_offer: None,
+				takeback_request: None,
+				clock: None,
+				first_player: Player::One,
+				swapped: false,
+				moves_played: 0,
+			}
+		}
+
+		/// the account playing as `player`.
+		pub fn account(&self, player: Player) -> &AccountId {
+			match player {
+				Player::One => &self.player_1,
+				Player::Two => &self.player_2,
 			}
 		}
 
 		pub fn set_state(&mut self, state: Vec<Vec<Coin>>) {
-			self.state = Some(state);
+			self.state = Some(Bitboards::from_grid(&state));
+		}
+
+		/// the grid of coins on the board, bottom row first.
+		pub fn grid(&self) -> Option<Vec<Vec<Coin>>> {
+			self.state.as_ref().map(|state| state.to_grid(self.settings.rows as usize))
 		}
 
 		/// generates the empty board
-		fn create_game_board(&mut self) -> Result<(), GameError> {
+		pub(crate) fn create_game_board(&mut self) -> Result<(), GameError> {
 			match self.state {
 				None => {
-					let rows = 6;
-					let columns = 7;
-					let mut state = Vec::with_capacity(rows);
-					for _ in 0..rows {
-						let mut row = Vec::with_capacity(columns);
-						for _ in 0..columns {
-							row.push(Coin::Empty);
-						}
-						state.push(row);
-					}
-					self.state = Some(state);
+					self.state = Some(Bitboards::new(self.settings.columns));
 					self.active = true;
 					Ok(())
 				},
@@ -163,120 +848,140 @@ pub mod pallet {
 				WinState::Player(player) => return WinState::Player(player),
 				WinState::Draw => return WinState::Draw,
 			};
-			// unwrap the state, which is an option
-			let state = self.state.as_ref().unwrap();
-			// check horizontally to find 4 consecutive 1s or 2s
-			for j in 0..6 {
-				for i in 3..7 {
-					let current: Coin = state[j][i];
-					if current != Coin::Empty {
-						let mut won = true;
-						for val in &state[j][(i - 3)..(i + 1)] {
-							if *val != current {
-								won = false;
-								break;
-							}
-						}
-						if won {
-							log::info!("Horizontal winning condition: j: {j}. Winner={current:?}");
-							if current == Coin::Player1 {
-								self.active = false;
-								self.state = None;
-								self.win_state = WinState::Player(Player::One);
-								return self.win_state;
-							} else if current == Coin::Player2 {
-								self.active = false;
-								self.state = None;
-								self.win_state = WinState::Player(Player::Two);
-								return self.win_state;
-							};
-						}
-					};
-				}
-			}
-			// check vertically to find 4 consecutive 1s or 2s
-			for i in 0..7 {
-				for j in 3..6 {
-					let current = state[j][i];
-					let mut won = true;
-					if current != Coin::Empty {
-						for k in (j - 3)..(j + 1) {
-							let val = &state[k][i];
-							if *val != current {
-								won = false;
-								break;
-							}
-						}
-						if won {
-							log::info!("Vertical winning condition: j: {j}. Winner={current:?}");
-							if current == Coin::Player1 {
-								self.active = false;
-								self.state = None;
-								self.win_state = WinState::Player(Player::One);
-								return self.win_state;
-							} else if current == Coin::Player2 {
-								self.active = false;
-								self.state = None;
-								self.win_state = WinState::Player(Player::Two);
-								return self.win_state;
-							};
-						}
-					};
-				}
+			if self.state.is_none() {
+				return WinState::Ongoing
 			}
-			// check diagonally to find 4 consecutive 1s or 2s
-			for i in 0..4 {
-				for j in 0..3 {
-					// check 1 set of diagonals
-					if (state[j][i] == state[j + 1][i + 1])
-						&& (state[j][i] == state[j + 2][i + 2])
-						&& (state[j][i] == state[j + 3][i + 3])
-						&& (state[j][i] != Coin::Empty)
-					{
-						let current = state[j][i];
-						log::info!("Diagonal-1 winning condition: j: {j}. Winner={current:?}");
-						if current == Coin::Player1 {
-							self.active = false;
-							self.state = None;
-							self.win_state = WinState::Player(Player::One);
-							return self.win_state;
-						} else if current == Coin::Player2 {
-							self.active = false;
-							self.state = None;
-							self.win_state = WinState::Player(Player::Two);
-							return self.win_state;
-						};
-					} else if (state[j + 3][i] == state[j + 2][i + 1])
-						&& (state[j + 3][i] == state[j + 1][i + 2])
-						&& (state[j + 3][i] == state[j][i + 3])
-						&& (state[j + 3][i] != Coin::Empty)
-					{
-						let current = state[j + 3][i];
-						log::info!("Diagonal-2 winning condition: j: {j}. Winner={current:?}");
-						if current == Coin::Player1 {
-							self.active = false;
-							self.state = None;
-							self.win_state = WinState::Player(Player::One);
-							return self.win_state;
-						} else if current == Coin::Player2 {
-							self.active = false;
-							self.state = None;
-							self.win_state = WinState::Player(Player::Two);
-							return self.win_state;
-						};
-					};
-				}
+			// check every direction to find `win_length` consecutive 1s or 2s
+			let winner = match (self.find_line(Coin::Player1), self.find_line(Coin::Player2)) {
+				// only a pop can complete lines for both players, the popper wins.
+				(Some(line_1), Some(line_2)) => match self.last_mover() {
+					Player::One => Some((Player::One, line_1)),
+					Player::Two => Some((Player::Two, line_2)),
+				},
+				(Some(line), None) => Some((Player::One, line)),
+				(None, Some(line)) => Some((Player::Two, line)),
+				(None, None) => None,
+			};
+			if let Some((winner, line)) = winner {
+				self.end_game(WinState::Player(winner));
+				self.winning_line = Some(line);
+				return self.win_state
 			}
 			// no winner
 			if self.is_playable() {
 				WinState::Ongoing
 			} else {
-				self.win_state = WinState::Draw;
-				self.active = false;
-				self.state = None;
+				self.end_game(WinState::Draw);
 				WinState::Draw
 			}
 		}
+
+		/// finds `win_length` consecutive slots of `coin` horizontally, vertically or
+		/// diagonally.
+		fn find_line(&self, coin: Coin) -> Option<WinningLine> {
+			let state = self.state.as_ref()?;
+			let coins = match coin {
+				Coin::Player1 => state.player_1,
+				Coin::Player2 => state.player_2,
+				Coin::Empty => return None,
+			};
+			let rows = self.settings.rows as usize;
+			let length = self.settings.win_length as isize;
+			let starts =
+				DIRECTIONS.map(|direction| line_starts(coins, rows, direction.step(), length));
+			if starts.iter().all(|starts| *starts == 0) {
+				return None
+			}
+			// report the first line found when scanning the board row by row.
+			for row in 0..rows {
+				for column in 0..self.settings.columns as usize {
+					for (direction, starts) in DIRECTIONS.into_iter().zip(starts) {
+						if starts & slot(rows, row, column) == 0 {
+							continue
+						}
+						log::info!(
+							"Winning condition: row: {row}, column: {column}, \
+							direction: {direction:?}. Winner={coin:?}"
+						);
+						let (row_step, column_step) = direction.step();
+						let cells = (0..length)
+							.map(|k| {
+								let r = row as isize + row_step * k;
+								let c = column as isize + column_step * k;
+								(r as u8, c as u8)
+							})
+							.collect::<Vec<_>>();
+						let cells = BoundedVec::truncate_from(cells);
+						return Some(WinningLine { direction, cells })
+					}
+				}
+			}
+			None
+		}
+
+		/// ends the game with the given result, keeping the final position.
+		pub(crate) fn end_game(&mut self, win_state: WinState) {
+			self.active = false;
+			self.win_state = win_state;
+			self.draw_offer = None;
+			self.takeback_request = None;
+		}
+
+		/// checks that it is `player`'s turn to move.
+		fn ensure_turn(&self, player: &AccountId) -> Result<(), GameError> {
+			let not_your_move = match &self.last_played {
+				Some(last_played) => player == last_played,
+				None => player != self.account(self.first_player),
+			};
+			if not_your_move {
+				return Err(GameError::NotYourMove);
+			};
+			Ok(())
+		}
+
+		/// the result of the game for a pairing where `player_1` is player 1, which differs
+		/// from `win_state` when the players have swapped sides.
+		pub fn result_for(&self, player_1: &AccountId) -> WinState {
+			if *player_1 == self.player_1 {
+				self.win_state
+			} else {
+				self.win_state.swapped()
+			}
+		}
+
+		/// gives the opening coin to the second player, who becomes the player of the first
+		/// move, so that the player who made it is to move.
+		pub(crate) fn swap_sides(&mut self) {
+			sp_std::mem::swap(&mut self.player_1, &mut self.player_2);
+			self.last_played = Some(self.account(self.first_player).clone());
+			// the players keep their own time.
+			self.clock = self.clock.map(|clock| Clock {
+				player_1: clock.player_2,
+				player_2: clock.player_1,
+			});
+			self.swapped = true;
+			self.draw_offer = None;
+			self.takeback_request = None;
+		}
+
+		/// the player who made the last move, or the first player if no move was made.
+		pub(crate) fn last_mover(&self) -> Player {
+			match &self.last_played {
+				Some(last_played) if *last_played == self.player_1 => Player::One,
+				Some(_) => Player::Two,
+				None => self.first_player,
+			}
+		}
+
+		/// the player who has to make the next move.
+		pub fn player_to_move(&self) -> Player {
+			match &self.last_played {
+				Some(last_played) if *last_played == self.player_1 => Player::Two,
+				Some(_) => Player::One,
+				None => self.first_player,
+			}
+		}
+
 		/// checks if the game has been won.
 		pub fn has_winner(&mut self) -> bool {
 			let win_state = self.get_winner();
@@ -293,12 +998,19 @@ pub mod pallet {
 				Some(s) => s,
 				None => return false,
 			};
-			for row in state {
-				for column in row {
-					if column == &Coin::Empty {
-						return true;
-					}
-				}
+			let rows = self.settings.rows as usize;
+			let columns = self.settings.columns as usize;
+			let full = (0..columns).fold(0, |full, column| full | column_mask(rows, column));
+			if (state.player_1 | state.player_2) != full {
+				return true;
+			}
+			// a full PopOut board can still be played by popping one of your own coins.
+			if self.settings.variant == GameVariant::PopOut {
+				let coins = match self.player_to_move() {
+					Player::One => state.player_1,
+					Player::Two => state.player_2,
+				};
+				return (0..columns).any(|column| coins & slot(rows, 0, column) != 0)
 			}
 			return false;
 		}
@@ -314,52 +1026,90 @@ pub mod pallet {
 			if self.has_winner() {
 				return Err(GameError::GameEnded);
 			} else if !self.is_playable() {
-				// Is this the right place for this?
-				self.state = None;
 				return Err(GameError::GameEnded);
 			}
 			// check if the game is unplayable (i.e., no empty slots anywhere.)
 			// check if it's the player's turn
-			let not_your_move = match &self.last_played {
-				Some(last_played) => player == *last_played,
-				None => player != self.player_1,
-			};
-			if not_your_move {
-				return Err(GameError::NotYourMove);
-			};
-			// board doesn't have more than 7 columns
-			if column >= 7 {
+			self.ensure_turn(&player)?;
+			// the column has to be on the board
+			if column >= self.settings.columns as usize {
 				return Err(GameError::InvalidColumnForMove);
 			};
-			let mut has_empty_slot = false;
-			let mut empty_slot = 0;
-			match &self.state {
-				None => Err(GameError::BoardNotReady),
-				Some(state) => {
-					let mut new_state = state.clone();
-					for row in 0..6 {
-						if new_state[row][column as usize] == Coin::Empty {
-							has_empty_slot = true;
-							empty_slot = row;
-							break;
-						}
-					}
-					if has_empty_slot {
-						if self.player_1 == player {
-							new_state[empty_slot][column] = Coin::Player1;
-						} else {
-							new_state[empty_slot][column] = Coin::Player2;
-						}
-						self.state = Some(new_state);
-						self.last_played = Some(player);
-						_ = self.get_winner();
+			let rows = self.settings.rows;
+			let is_player_1 = self.player_1 == player;
+			let state = self.state.as_mut().ok_or(GameError::BoardNotReady)?;
+			let height = state.heights[column];
+			if height >= rows {
+				return Err(GameError::ColumnFull);
+			}
+			let bit = slot(rows as usize, height as usize, column);
+			if is_player_1 {
+				state.player_1 |= bit;
+			} else {
+				state.player_2 |= bit;
+			}
+			state.heights[column] = height + 1;
+			self.last_played = Some(player);
+			// a move declines any pending draw offer or takeback request.
+			self.draw_offer = None;
+			self.takeback_request = None;
+			_ = self.get_winner();
+			Ok(())
+		}
 
-						Ok(())
-					} else {
-						Err(GameError::ColumnFull)
-					}
-				},
+		/// pop one of the player's own coins out of the bottom of a column (PopOut only).
+		fn pop(&mut self, player: AccountId, column: usize) -> Result<(), GameError> {
+			if self.settings.variant != GameVariant::PopOut {
+				return Err(GameError::PopNotAllowed)
+			}
+			if self.state.is_none() {
+				return Err(GameError::BoardNotReady)
+			}
+			if self.has_winner() {
+				return Err(GameError::GameEnded)
+			}
+			self.ensure_turn(&player)?;
+			if column >= self.settings.columns as usize {
+				return Err(GameError::InvalidColumnForMove)
+			};
+			let is_player_1 = self.player_1 == player;
+			let rows = self.settings.rows as usize;
+			let state = self.state.as_mut().ok_or(GameError::BoardNotReady)?;
+			let coins = if is_player_1 { state.player_1 } else { state.player_2 };
+			if coins & slot(rows, 0, column) == 0 {
+				return Err(GameError::NotYourCoin)
+			}
+			// the column shifts down by one slot.
+			let mask = column_mask(rows, column);
+			state.player_1 = (state.player_1 & !mask) | (((state.player_1 & mask) >> 1) & mask);
+			state.player_2 = (state.player_2 & !mask) | (((state.player_2 & mask) >> 1) & mask);
+			state.heights[column] = state.heights[column].saturating_sub(1);
+			self.last_played = Some(player);
+			// a move declines any pending draw offer or takeback request.
+			self.draw_offer = None;
+			self.takeback_request = None;
+			_ = self.get_winner();
+			Ok(())
+		}
+	}
+
+	impl ConnectFourBoardStruct<Player> {
+		/// Plays `moves` on an empty board with the given settings, where the players are
+		/// identified by `Player` instead of by account. The player of the first move is the
+		/// first player.
+		pub fn replay(settings: GameSettings, moves: &[Move]) -> Result<Self, GameError> {
+			let mut board = Self::new(H256::zero(), Player::One, Player::Two, settings);
+			board.first_player = moves.first().map_or(Player::One, |first_move| first_move.player);
+			board.challenge_accepted = true;
+			board.create_game_board()?;
+			for next_move in moves {
+				let column = next_move.column as usize;
+				match next_move.kind {
+					MoveKind::Drop => board.play(next_move.player, column)?,
+					MoveKind::Pop => board.pop(next_move.player, column)?,
+				}
 			}
+			Ok(board)
 		}
 	}
 
@@ -369,14 +1119,42 @@ pub mod pallet {
 		0
 	}
 
-	/// Nonce storage so that random hashes can be calculated.
+	/// Nonce storage so that random hashes and board IDs can be calculated.
 	#[pallet::storage]
 	pub type Nonce<T: Config> = StorageValue<_, u64, ValueQuery, NonceDefault<T>>;
 
-	/// StorageValue to list all boards
+	/// The number of boards created so far, which is the `GameIndex` of the next board.
+	#[pallet::storage]
+	#[pallet::getter(fn get_game_count)]
+	pub type GameCount<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+	/// StorageMap of the boards by their sequential index, a short way to refer to a game.
+	/// The index of a board is removed along with the board.
+	#[pallet::storage]
+	#[pallet::getter(fn get_board_by_index)]
+	pub type GameIndex<T: Config> = StorageMap<_, Twox64Concat, u64, H256, OptionQuery>;
+
+	/// StorageMap of all boards to their `GameIndex`, counted so that the number of boards
+	/// is known without iterating them. See `board_ids` to page through them.
+	#[pallet::storage]
+	#[pallet::getter(fn get_board_index)]
+	pub(super) type ConnectFourBoards<T: Config> =
+		CountedStorageMap<_, Blake2_128Concat, H256, u64, OptionQuery>;
+
+	/// StorageMap of the deposit reserved for every board, and the account it is paid back to.
+	#[pallet::storage]
+	#[pallet::getter(fn get_deposit)]
+	pub(super) type Deposits<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, (T::AccountId, BalanceOf<T>), OptionQuery>;
+
+	/// Queue of finished boards that can be removed from a given block on.
+	#[pallet::storage]
+	pub(super) type PruneQueue<T: Config> =
+		StorageMap<_, Twox64Concat, BlockNumber, BlockQueue<T>, ValueQuery>;
+
+	/// The first block of the `PruneQueue` whose boards haven't all been removed yet.
 	#[pallet::storage]
-	#[pallet::getter(fn get_all_board_ids)]
-	pub(super) type ConnectFourBoards<T: Config> = StorageValue<_, Vec<H256>, ValueQuery>;
+	pub(super) type NextPruneBlock<T: Config> = StorageValue<_, BlockNumber, ValueQuery>;
 
 	/// StorageMap to keep a board per user
 	#[pallet::storage]
@@ -384,22 +1162,135 @@ pub mod pallet {
 	pub(super) type ConnectFourBoardById<T: Config> =
 		StorageMap<_, Blake2_128Concat, H256, ConnectFourBoardStruct<T::AccountId>, OptionQuery>;
 
-	/// StorageMap to relate account IDs to the challenges
+	/// StorageMap to relate pairs of account IDs to their pending challenges and ongoing games.
+	/// Every board is listed under both (player_1, player_2) and (player_2, player_1), up to
+	/// `MaxGamesPerPair` boards.
 	#[pallet::storage]
-	#[pallet::getter(fn get_challenge)]
+	#[pallet::getter(fn get_challenges)]
 	pub(super) type Challenges<T: Config> = StorageDoubleMap<
 		_,
 		Blake2_128Concat,
-		// Recipient
+		// Player
 		T::AccountId,
 		Blake2_128Concat,
-		// challenger
+		// Other player
 		T::AccountId,
-		// Board ID
+		// Board IDs
+		BoundedVec<H256, T::MaxGamesPerPair>,
+		ValueQuery,
+	>;
+
+	/// StorageMap with the moves made in every game, in the order they were played.
+	#[pallet::storage]
+	#[pallet::getter(fn get_move_history)]
+	pub(super) type MoveHistory<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, BoundedVec<Move, T::MaxMoves>, ValueQuery>;
+
+	/// StorageMap with the amount each player stakes on a game, for staked games.
+	#[pallet::storage]
+	#[pallet::getter(fn get_stake)]
+	pub(super) type Stakes<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, BalanceOf<T>, OptionQuery>;
+
+	/// StorageMap with the bets placed on every game, in the order they were placed.
+	#[pallet::storage]
+	#[pallet::getter(fn get_bets)]
+	pub(super) type Bets<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		H256,
+		BoundedVec<Bet<T::AccountId, BalanceOf<T>>, T::MaxBetsPerGame>,
+		ValueQuery,
+	>;
+
+	/// Expiry queue of boards whose player to move times out at a given block.
+	/// NOTE: entries are not removed when a move is made, they are checked against
+	/// the board's `last_move_at` when the block is reached.
+	#[pallet::storage]
+	pub(super) type MoveDeadlines<T: Config> =
+		StorageMap<_, Twox64Concat, BlockNumber, BlockQueue<T>, ValueQuery>;
+
+	/// The first block of the `MoveDeadlines` whose boards haven't all been checked yet.
+	#[pallet::storage]
+	pub(super) type NextDeadlineBlock<T: Config> = StorageValue<_, BlockNumber, ValueQuery>;
+
+	/// The lobby: open challenges by board ID, waiting for an opponent.
+	#[pallet::storage]
+	#[pallet::getter(fn get_open_challenge)]
+	pub(super) type Lobby<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, OpenChallenge<T::AccountId>, OptionQuery>;
+
+	/// Expiry queue of open challenges that leave the lobby at a given block.
+	#[pallet::storage]
+	pub(super) type LobbyExpiries<T: Config> =
+		StorageMap<_, Twox64Concat, BlockNumber, BlockQueue<T>, ValueQuery>;
+
+	/// Single-elimination tournaments, by tournament ID.
+	#[pallet::storage]
+	#[pallet::getter(fn get_tournament)]
+	pub(super) type Tournaments<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, Tournament<T>, OptionQuery>;
+
+	/// The tournament each tournament game is played in, by board ID.
+	#[pallet::storage]
+	#[pallet::getter(fn get_tournament_of_board)]
+	pub(super) type TournamentBoards<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, H256, OptionQuery>;
+
+	/// Swiss tournaments, by tournament ID.
+	#[pallet::storage]
+	#[pallet::getter(fn get_swiss_tournament)]
+	pub(super) type SwissTournaments<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, SwissTournament<T>, OptionQuery>;
+
+	/// The Swiss tournament each Swiss tournament game is played in, by board ID.
+	#[pallet::storage]
+	#[pallet::getter(fn get_swiss_tournament_of_board)]
+	pub(super) type SwissBoards<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, H256, OptionQuery>;
+
+	/// Round-robin leagues, by league ID.
+	#[pallet::storage]
+	#[pallet::getter(fn get_league)]
+	pub(super) type Leagues<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, League<T>, OptionQuery>;
+
+	/// The league each league game is played in, by board ID.
+	#[pallet::storage]
+	#[pallet::getter(fn get_league_of_board)]
+	pub(super) type LeagueBoards<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, H256, OptionQuery>;
+
+	/// The standings of every player of a league, by league ID and player.
+	#[pallet::storage]
+	#[pallet::getter(fn get_league_standing)]
+	pub(super) type LeagueStandings<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
 		H256,
+		Blake2_128Concat,
+		T::AccountId,
+		ScoreCard,
 		ValueQuery,
 	>;
 
+	/// Queue of leagues whose season closes at a given block.
+	#[pallet::storage]
+	pub(super) type LeagueEnds<T: Config> =
+		StorageMap<_, Twox64Concat, BlockNumber, BlockQueue<T>, ValueQuery>;
+
+	// Default value for AccountRating
+	#[pallet::type_value]
+	pub fn RatingDefault<T: Config>() -> u32 {
+		T::InitialRating::get()
+	}
+
+	/// StorageMap for Elo ratings
+	#[pallet::storage]
+	#[pallet::getter(fn get_rating)]
+	pub(super) type AccountRating<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery, RatingDefault<T>>;
+
 	/// StorageMap for scorecards
 	#[pallet::storage]
 	#[pallet::getter(fn get_scorecard)]
@@ -415,22 +1306,97 @@ pub mod pallet {
 		ChallengeAccepted(T::AccountId, T::AccountId),
 		/// challenge denied
 		ChallengeDenied(T::AccountId, T::AccountId),
+		/// challenge withdrawn by the challenger
+		ChallengeWithdrawn(T::AccountId, T::AccountId),
 		/// A new Game got created.
 		GameCreated(H256),
 		/// Game ended
 		GameEnded(H256),
 		/// A user has played a move
 		MoveMade(T::AccountId, T::AccountId),
-		/// A user has won a game,
-		GameWon(T::AccountId, H256),
+		/// A user has won a game, with the line of coins that won it unless the opponent
+		/// resigned or timed out.
+		GameWon(T::AccountId, H256, Option<WinningLine>),
 		/// a game was drawn.
 		GameDrawn(H256),
+		/// An open challenge has been listed in the lobby.
+		OpenChallengeCreated(T::AccountId, H256),
+		/// An account has joined an open challenge.
+		OpenChallengeJoined(T::AccountId, H256),
+		/// An open challenge was cancelled by its creator.
+		OpenChallengeCancelled(H256),
+		/// An open challenge expired without anyone joining it.
+		OpenChallengeExpired(H256),
+		/// A user has popped a coin out of a column.
+		CoinPopped(T::AccountId, H256),
+		/// A user has resigned from a game.
+		PlayerResigned(T::AccountId, H256),
+		/// A user did not move in time and forfeited the game.
+		GameTimedOut(T::AccountId, H256),
+		/// A user's Elo rating changed from the first value to the second.
+		RatingChanged(T::AccountId, u32, u32),
+		/// A user has offered a draw.
+		DrawOffered(T::AccountId, H256),
+		/// A user has declined a draw offer.
+		DrawDeclined(T::AccountId, H256),
+		/// A user has asked to take back their last move.
+		TakebackRequested(T::AccountId, H256),
+		/// A user has let their opponent take back their last move.
+		TakebackAccepted(T::AccountId, H256),
+		/// Both players of a game have staked the given amount on it.
+		StakesReserved(H256, BalanceOf<T>),
+		/// A user has won the pot of a game, after the house fee.
+		PotWon(T::AccountId, H256, BalanceOf<T>),
+		/// The pot of a drawn game was split, each player got the given amount back.
+		PotSplit(H256, BalanceOf<T>),
+		/// A user has created a tournament.
+		TournamentCreated(T::AccountId, H256),
+		/// A user has registered for a tournament.
+		TournamentJoined(T::AccountId, H256),
+		/// The games of a round of a tournament have been created.
+		TournamentRoundStarted(H256, u32),
+		/// Every match of a round of a tournament has a winner, and the next round can start.
+		TournamentRoundFinished(H256, u32),
+		/// A user has won a tournament.
+		TournamentWon(T::AccountId, H256),
+		/// A user has created a league.
+		LeagueCreated(T::AccountId, H256),
+		/// A user has registered for a league.
+		LeagueJoined(T::AccountId, H256),
+		/// The season of a league has started, and closes at the given block.
+		LeagueStarted(H256, BlockNumber),
+		/// The games of a round of a league have been created.
+		LeagueRoundStarted(H256, u32),
+		/// The season of a league has closed, with the final standings, best first.
+		LeagueClosed(H256, Vec<T::AccountId>),
+		/// A user has created a Swiss tournament.
+		SwissTournamentCreated(T::AccountId, H256),
+		/// A user has registered for a Swiss tournament.
+		SwissTournamentJoined(T::AccountId, H256),
+		/// The games of a round of a Swiss tournament have been created.
+		SwissRoundStarted(H256, u32),
+		/// Every game of a round of a Swiss tournament has ended, and the next round can be
+		/// paired.
+		SwissRoundFinished(H256, u32),
+		/// The last round of a Swiss tournament has been played, with the final standings,
+		/// best first.
+		SwissTournamentFinished(H256, Vec<T::AccountId>),
+		/// A user has bet an amount on the result of a game.
+		BetPlaced(T::AccountId, H256, WinState, BalanceOf<T>),
+		/// The bets on a game have been paid out, from a pot of the given amount.
+		BetsSettled(H256, BalanceOf<T>),
+		/// The bets on a game have been refunded.
+		BetsRefunded(H256),
+		/// A user has taken the opening coin of a game as their own, under the swap rule.
+		SidesSwapped(T::AccountId, H256),
+		/// A finished board has been removed from storage.
+		GamePruned(H256),
 	}
 
 	// Errors inform users that something went wrong.
 	#[pallet::error]
 	pub enum Error<T> {
-		/// A challenge already exists between these two accounts.
+		/// The maximum number of challenges already exists between these two accounts.
 		ChallengeExists,
 		/// A game already exists between these two accounts.
 		ActiveGameExists,
@@ -467,59 +1433,210 @@ pub mod pallet {
 		UnknownError,
 		/// The challenge hasn't yet been accepted
 		ChallengeNotYetAccepted,
+		/// The board dimensions or win length are out of bounds.
+		InvalidGameSettings,
+		/// Coins can only be popped in PopOut games.
+		PopNotAllowed,
+		/// The bottom coin of that column is not yours to pop.
+		NotYourCoin,
+		/// There is no such open challenge in the lobby.
+		OpenChallengeDoesNotExist,
+		/// Only the creator of an open challenge can cancel it.
+		NotYourChallenge,
+		/// You cannot decline your own challenge, withdraw it instead.
+		CannotDeclineYourOwnChallenge,
+		/// You cannot withdraw a challenge you received, decline it instead.
+		CannotWithdrawReceivedChallenge,
+		/// A draw has already been offered in this game.
+		DrawAlreadyOffered,
+		/// No draw has been offered in this game.
+		NoDrawOffer,
+		/// Only your opponent can accept or decline your draw offer.
+		CannotAnswerYourOwnDrawOffer,
+		/// Takebacks are switched off for rated games.
+		TakebacksNotAllowed,
+		/// You can only take back your own last move.
+		NotYourLastMove,
+		/// A takeback has already been requested in this game.
+		TakebackAlreadyRequested,
+		/// No takeback has been requested in this game.
+		NoTakebackRequest,
+		/// Only your opponent can accept your takeback request.
+		CannotAcceptYourOwnTakeback,
+		/// One of the players cannot afford the stake of the game.
+		CannotAffordStake,
+		/// There is no such tournament.
+		TournamentDoesNotExist,
+		/// A tournament needs between 2 and `MaxTournamentPlayers` players.
+		InvalidTournamentSize,
+		/// The tournament has already started.
+		TournamentRegistrationClosed,
+		/// The tournament has as many players as it can have.
+		TournamentFull,
+		/// You have already registered for this tournament or league.
+		AlreadyRegistered,
+		/// Only the creator of a tournament can start it.
+		NotYourTournament,
+		/// The tournament hasn't started, or has finished.
+		TournamentNotRunning,
+		/// A tournament or league needs at least 2 players to start.
+		NotEnoughPlayers,
+		/// There is no such league.
+		LeagueDoesNotExist,
+		/// A league needs between 2 and `MaxLeaguePlayers` players.
+		InvalidLeagueSize,
+		/// The season of the league has already started.
+		LeagueRegistrationClosed,
+		/// The league has as many players as it can have.
+		LeagueFull,
+		/// Only the creator of a league can start it.
+		NotYourLeague,
+		/// The season of the league hasn't started, or has closed.
+		LeagueNotRunning,
+		/// Games of the current round are still being played.
+		RoundNotFinished,
+		/// Every round has already been started.
+		NoRoundsLeft,
+		/// There is no such Swiss tournament.
+		SwissTournamentDoesNotExist,
+		/// A Swiss tournament needs at least one round, and fewer rounds than players.
+		InvalidSwissRounds,
+		/// You cannot bet on a game you play in.
+		CannotBetOnYourOwnGame,
+		/// Bets can only be placed on a win for either player, or a draw.
+		InvalidBetOutcome,
+		/// The bet is smaller than `MinimumBet`.
+		BetTooSmall,
+		/// No more bets can be placed on this game, `BettingCutoff` moves have been played.
+		BettingClosed,
+		/// The game already has as many bets as it can have.
+		TooManyBets,
+		/// You cannot afford the bet.
+		CannotAffordBet,
+		/// The game isn't played with a clock.
+		NoTimeControl,
+		/// Your opponent isn't to move, or still has time on their clock.
+		OpponentHasTimeLeft,
+		/// The game isn't played with the swap rule.
+		SwapRuleNotInPlay,
+		/// Sides can only be swapped by the second player, instead of their first move.
+		CannotSwapSides,
+		/// A board with the same ID already exists.
+		BoardIdTaken,
+		/// A tournament, league or Swiss tournament with the same ID already exists.
+		CompetitionIdTaken,
+		/// You cannot afford the deposit for a board.
+		CannotAffordDeposit,
+		/// The game hasn't finished yet.
+		GameNotFinished,
+		/// The game ended fewer than `PruneAfter` blocks ago.
+		GameTooRecent,
+		/// The boards stored before the last runtime upgrade are still being migrated.
+		MigrationInProgress,
+		/// The queues of the blocks a board would be queued for are full, try again later.
+		QueuesFull,
+		/// Games in tournaments and leagues start with the first player of the pairing,
+		/// without the swap rule.
+		InvalidCompetitionSettings,
 	}
 
-	// Dispatchable functions allows users to interact with the pallet and invoke state changes.
-	// These functions materialize as "extrinsics", which are often compared to transactions.
-	// Dispatchable functions must be annotated with a weight and must return a DispatchResult.
-	#[pallet::call]
-	impl<T: Config> Pallet<T> {
-		/// Challenge an account to a new game.
-		#[pallet::weight(10_000)]
-		pub fn challenge_player(origin: OriginFor<T>, opponent: T::AccountId) -> DispatchResult {
-			let challenger = ensure_signed(origin)?;
-			// Check if the user is trying to challenge themselves to a game.
-			ensure!(challenger != opponent, <Error<T>>::CannotPlayYourself);
-			// check if a challenge exists already, made by either party.
-			let challenge_received = <Challenges<T>>::contains_key(&opponent, &challenger);
-			log::info!(
-				"Challenge already received={} from {:?} to {:?}",
-				challenge_received,
-				opponent,
-				challenger
-			);
-			let challenge_sent = <Challenges<T>>::contains_key(&challenger, &opponent);
-			log::info!(
-				"Challenge already sent={} from {:?} to {:?}",
-				challenge_sent,
-				challenger,
-				opponent
-			);
-			let challenge_exists = challenge_received || challenge_sent;
-			log::info!("Challenge exists: {}", challenge_exists);
-			ensure!(!challenge_exists, <Error<T>>::ChallengeExists);
-			// no challenge exists between these two.
-			// Create new board
-			// Using a subject is recommended to prevent accidental re-use of the seed
-			// (This does not add security or entropy)
-			let subject = Self::encode_and_update_nonce();
-			let (board_id, _) = T::RandomnessSource::random(&subject);
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: T::BlockNumber) -> Weight {
+			let now: BlockNumber = n.unique_saturated_into();
+			// boards are migrated, and moves expired, in at most a tenth of the block each.
+			let limit = Perbill::from_percent(10) * T::BlockWeights::get().max_block;
+			migrations::v1::migrate_boards::<T>(limit)
+				.saturating_add(Self::expire_moves(now, limit))
+				.saturating_add(Self::expire_open_challenges(now))
+				.saturating_add(Self::close_leagues(now))
+		}
 
+		fn on_idle(n: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			Self::prune_finished_games(n.unique_saturated_into(), remaining_weight)
+		}
+
+		fn on_runtime_upgrade() -> Weight {
+			migrations::v1::migrate::<T>()
+		}
+
+		fn integrity_test() {
+			// the largest board, with an empty bit on top of every column, has to fit in the
+			// bitboards.
+			assert!(
+				(T::MaxRows::get() + 1).saturating_mul(T::MaxColumns::get()) <= MAX_BOARD_SLOTS,
+				"boards of MaxRows x MaxColumns don't fit in the bitboards"
+			);
+			assert!(T::MaxQueuedPerBlock::get() > 0, "MaxQueuedPerBlock must be at least 1");
+			// a standard game can always be played until the board is full.
+			assert!(
+				T::MaxMoves::get() >= T::MaxRows::get().saturating_mul(T::MaxColumns::get()),
+				"MaxMoves must be at least MaxRows * MaxColumns"
+			);
+		}
+	}
+
+	impl<T> From<GameError> for Error<T> {
+		fn from(error: GameError) -> Self {
+			match error {
+				GameError::BoardExistsError => Error::<T>::BoardExistsError,
+				GameError::NotYourMove => Error::<T>::NotYourMove,
+				GameError::NoPlayableMove => Error::<T>::NoPlayableMove,
+				GameError::InvalidColumnForMove => Error::<T>::InvalidColumnForMove,
+				GameError::ColumnFull => Error::<T>::ColumnFull,
+				GameError::BoardNotReady => Error::<T>::BoardNotReady,
+				GameError::GameEnded => Error::<T>::GameEnded,
+				GameError::PopNotAllowed => Error::<T>::PopNotAllowed,
+				GameError::NotYourCoin => Error::<T>::NotYourCoin,
+			}
+		}
+	}
+
+	// Dispatchable functions allows users to interact with the pallet and invoke state changes.
+	// These functions materialize as "extrinsics", which are often compared to transactions.
+	// Dispatchable functions must be annotated with a weight and must return a DispatchResult.
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Challenge an account to a new game, played with the given settings. With a
+		/// `stake`, both players stake that amount on the game once it is accepted.
+		#[pallet::weight(10_000)]
+		pub fn challenge_player(
+			origin: OriginFor<T>,
+			opponent: T::AccountId,
+			settings: GameSettings,
+			stake: Option<BalanceOf<T>>,
+		) -> DispatchResult {
+			let challenger = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			// Check if the user is trying to challenge themselves to a game.
+			ensure!(challenger != opponent, <Error<T>>::CannotPlayYourself);
+			ensure!(
+				settings.is_valid(T::MaxRows::get(), T::MaxColumns::get()),
+				<Error<T>>::InvalidGameSettings
+			);
+			// check how many challenges exist already, made by either party.
+			let games = Self::get_challenges(&challenger, &opponent).len();
+			log::info!("{} games between {:?} and {:?}", games, challenger, opponent);
+			ensure!((games as u32) < T::MaxGamesPerPair::get(), <Error<T>>::ChallengeExists);
+			// Create new board
+			let board_id = Self::new_board_id(&challenger, Some(&opponent))?;
+			Self::reserve_deposit(&challenger, board_id)?;
 			let board = ConnectFourBoardStruct::<T::AccountId>::new(
 				board_id,
 				challenger.clone(),
 				opponent.clone(),
+				settings,
 			);
 			// insert the board in the map that has board_id x board
 			<ConnectFourBoardById<T>>::insert(&board_id, board);
+			// the stakes are only reserved when the challenge is accepted.
+			if let Some(stake) = stake.filter(|stake| !stake.is_zero()) {
+				<Stakes<T>>::insert(&board_id, stake);
+			}
 			// insert this board into the global list of boards;
-			let mut boards = <ConnectFourBoards<T>>::get();
-			boards.push(board_id);
-			<ConnectFourBoards<T>>::set(boards);
-			// insert the board_id in the map that has recipient x challenger board_id
-			<Challenges<T>>::insert(&opponent, &challenger, board_id);
-			// insert the board_id in the map that has challenger x recipient board_id
-			<Challenges<T>>::insert(&challenger, &opponent, board_id);
+			Self::index_board(board_id);
+			// add the board_id to the boards between challenger and recipient
+			Self::link_board(&challenger, &opponent, board_id);
 			// send an event saying challenge made
 			Self::deposit_event(Event::ChallengeReceived(opponent, challenger));
 			Self::deposit_event(Event::GameCreated(board_id));
@@ -527,149 +1644,760 @@ pub mod pallet {
 		}
 
 		/// Accept a challenge
-		#[pallet::weight(10_000)]
-		pub fn accept_challenge(origin: OriginFor<T>, challenger: T::AccountId) -> DispatchResult {
+		#[pallet::weight(10_000 + Pallet::<T>::enqueue_weight())]
+		pub fn accept_challenge(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
 			let challenged = ensure_signed(origin)?;
-			// cannot accept challenges made by self
-			ensure!(challenged != challenger, <Error<T>>::CannotPlayYourself);
-			let challenge_exists = <Challenges<T>>::contains_key(&challenged, &challenger);
-			ensure!(challenge_exists, <Error<T>>::ChallengeDoesNotExist);
-			let board_id = Self::get_challenge(&challenged, &challenger);
-			let mut board = Self::get_connect_four_board_by_id(&board_id).unwrap();
+			Self::ensure_migrated()?;
+			let mut board = Self::get_connect_four_board_by_id(&board_id)
+				.ok_or(<Error<T>>::ChallengeDoesNotExist)?;
 			// check whether the player is trying to accept their own challenge.
 			// this is doable because player_1 is set to the challenger.
 			let own_challenge = board.player_1 == challenged;
 			ensure!(!own_challenge, <Error<T>>::CannotAcceptYourOwnChallenge);
+			ensure!(board.player_2 == challenged, <Error<T>>::NotYourBoard);
 			let challenge_already_accepted = board.challenge_accepted;
 			ensure!(!challenge_already_accepted, <Error<T>>::ActiveGameExists);
+			let challenger = board.player_1.clone();
+			if let Some(stake) = Self::get_stake(&board_id) {
+				for player in [&challenger, &challenged] {
+					T::Currency::reserve(player, stake)
+						.map_err(|_| <Error<T>>::CannotAffordStake)?;
+				}
+				Self::deposit_event(Event::StakesReserved(board_id, stake));
+			}
 			// let user know if there's already an active game.
 			// there's no existing challenge so this can be accepted.
-			board.challenge_accepted = true;
-			match board.create_game_board() {
-				Ok(_) => (),
-				Err(_) => ensure!(false, <Error<T>>::ActiveGameExists),
-			};
-			let mut score_card_1 = Self::get_scorecard(&board.player_1);
-			let mut score_card_2 = Self::get_scorecard(&board.player_2);
-			score_card_1.played += 1;
-			score_card_2.played += 1;
-			<AccountScoreCard<T>>::set(&board.player_1, score_card_1);
-			<AccountScoreCard<T>>::set(&board.player_2, score_card_2);
-			<ConnectFourBoardById<T>>::insert(&board_id, board);
+			board.first_player = Self::choose_first_player(board.settings.first_move);
+			Self::start_game(board_id, board)?;
 			Self::deposit_event(Event::ChallengeAccepted(challenged, challenger));
 			Ok(())
 		}
 
-		/// play a move
+		/// Decline a challenge that was received.
 		#[pallet::weight(10_000)]
-		pub fn play(
-			origin: OriginFor<T>,
-			other_player: T::AccountId,
-			column: u32,
-		) -> DispatchResult {
+		pub fn decline_challenge(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let challenged = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let board = Self::get_connect_four_board_by_id(&board_id)
+				.ok_or(<Error<T>>::ChallengeDoesNotExist)?;
+			// player_1 is the challenger, they should withdraw instead.
+			ensure!(board.player_1 != challenged, <Error<T>>::CannotDeclineYourOwnChallenge);
+			ensure!(board.player_2 == challenged, <Error<T>>::NotYourBoard);
+			// an accepted challenge is an ongoing game and cannot be declined.
+			ensure!(!board.challenge_accepted, <Error<T>>::ActiveGameExists);
+			let challenger = board.player_1;
+			Self::remove_challenge(&challenged, &challenger, board_id);
+			Self::deposit_event(Event::ChallengeDenied(challenged, challenger));
+			Ok(())
+		}
+
+		/// Withdraw a challenge that was made, before it is accepted.
+		#[pallet::weight(10_000)]
+		pub fn withdraw_challenge(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let challenger = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let board = Self::get_connect_four_board_by_id(&board_id)
+				.ok_or(<Error<T>>::ChallengeDoesNotExist)?;
+			// only the challenger (player_1) can withdraw a challenge.
+			ensure!(board.player_2 != challenger, <Error<T>>::CannotWithdrawReceivedChallenge);
+			ensure!(board.player_1 == challenger, <Error<T>>::NotYourBoard);
+			ensure!(!board.challenge_accepted, <Error<T>>::ActiveGameExists);
+			let opponent = board.player_2;
+			Self::remove_challenge(&challenger, &opponent, board_id);
+			Self::deposit_event(Event::ChallengeWithdrawn(challenger, opponent));
+			Ok(())
+		}
+
+		/// List a challenge in the lobby, that any account can join.
+		#[pallet::weight(10_000 + Pallet::<T>::enqueue_weight())]
+		pub fn create_open_challenge(origin: OriginFor<T>, settings: GameSettings) -> DispatchResult {
+			let creator = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			ensure!(
+				settings.is_valid(T::MaxRows::get(), T::MaxColumns::get()),
+				<Error<T>>::InvalidGameSettings
+			);
+			let board_id = Self::new_board_id(&creator, None)?;
+			Self::reserve_deposit(&creator, board_id)?;
+			let expires_at = Self::enqueue::<LobbyExpiries<T>>(
+				Self::current_block().saturating_add(T::OpenChallengeExpiry::get()),
+				board_id,
+			)?;
+			let challenge = OpenChallenge { creator: creator.clone(), settings, expires_at };
+			<Lobby<T>>::insert(&board_id, challenge);
+			Self::deposit_event(Event::OpenChallengeCreated(creator, board_id));
+			Ok(())
+		}
+
+		/// Join an open challenge from the lobby, starting the game.
+		#[pallet::weight(10_000 + Pallet::<T>::enqueue_weight())]
+		pub fn join_open_challenge(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let opponent = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let challenge =
+				Self::get_open_challenge(&board_id).ok_or(<Error<T>>::OpenChallengeDoesNotExist)?;
+			let challenger = challenge.creator;
+			ensure!(challenger != opponent, <Error<T>>::CannotPlayYourself);
+			let games = Self::get_challenges(&challenger, &opponent).len();
+			ensure!((games as u32) < T::MaxGamesPerPair::get(), <Error<T>>::ChallengeExists);
+			<Lobby<T>>::remove(&board_id);
+			let mut board = ConnectFourBoardStruct::<T::AccountId>::new(
+				board_id,
+				challenger.clone(),
+				opponent.clone(),
+				challenge.settings,
+			);
+			board.first_player = Self::choose_first_player(challenge.settings.first_move);
+			Self::start_game(board_id, board)?;
+			Self::index_board(board_id);
+			Self::link_board(&challenger, &opponent, board_id);
+			Self::deposit_event(Event::OpenChallengeJoined(opponent.clone(), board_id));
+			Self::deposit_event(Event::GameCreated(board_id));
+			Self::deposit_event(Event::ChallengeAccepted(opponent, challenger));
+			Ok(())
+		}
+
+		/// Remove your open challenge from the lobby.
+		#[pallet::weight(10_000)]
+		pub fn cancel_open_challenge(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let creator = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let challenge =
+				Self::get_open_challenge(&board_id).ok_or(<Error<T>>::OpenChallengeDoesNotExist)?;
+			ensure!(challenge.creator == creator, <Error<T>>::NotYourChallenge);
+			<Lobby<T>>::remove(&board_id);
+			Self::return_deposit(board_id);
+			Self::deposit_event(Event::OpenChallengeCancelled(board_id));
+			Ok(())
+		}
+
+		/// play a move
+		#[pallet::weight(10_000 + Pallet::<T>::game_end_weight())]
+		pub fn play(origin: OriginFor<T>, board_id: H256, column: u32) -> DispatchResult {
 			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
 			// check if such a game exists
-			let game_exists = <Challenges<T>>::contains_key(&player, &other_player);
-			log::info!("Game between {:?} and {:?} exists={}", player, other_player, game_exists);
-			ensure!(game_exists, <Error<T>>::GameDoesNotExist);
-			// get the board ID
-			let board_id = Self::get_challenge(&player, &other_player);
+			let mut board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			let other_player = if player == board.player_1 {
+				board.player_2.clone()
+			} else if player == board.player_2 {
+				board.player_1.clone()
+			} else {
+				return Err(<Error<T>>::NotYourBoard.into())
+			};
 			// check if the board is active
-			let mut board = Self::get_connect_four_board_by_id(&board_id).unwrap();
 			ensure!(board.challenge_accepted, <Error<T>>::ChallengeNotYetAccepted);
 			ensure!(board.active, <Error<T>>::GameEnded);
-			match board.play(player.clone(), column as usize) {
-				Err(e) => {
-					// returning an error manually is complicated, lets use `ensure!`
-					let failure = false;
-					match e {
-						GameError::BoardExistsError => {
-							ensure!(failure, <Error<T>>::BoardExistsError)
-						},
-						GameError::BoardNotReady => ensure!(failure, <Error<T>>::BoardNotReady),
-						GameError::ColumnFull => ensure!(failure, <Error<T>>::ColumnFull),
-						GameError::InvalidColumnForMove => {
-							ensure!(failure, <Error<T>>::InvalidColumnForMove)
-						},
-						GameError::NoPlayableMove => ensure!(failure, <Error<T>>::NoPlayableMove),
-						GameError::NotYourMove => ensure!(failure, <Error<T>>::NotYourMove),
-						GameError::GameEnded => ensure!(failure, <Error<T>>::GameEnded),
-					}
-					ensure!(failure, <Error<T>>::UnknownError)
-				},
-				Ok(_) => {
-					// need to check if someone has won, or if the game is unplayable.
-					match board.get_winner() {
-						WinState::Player(winner) => {
-							<ConnectFourBoardById<T>>::insert(&board_id, board.clone());
-							// remove board from users' storagedoublemap
-							<Challenges<T>>::remove(&player, &other_player);
-							<Challenges<T>>::remove(&other_player, &player);
-							// emit event about the move
-							Self::deposit_event(Event::MoveMade(
-								player.clone(),
-								other_player.clone(),
-							));
-							// get the scorecards.
-							let mut score_card_1 = <AccountScoreCard<T>>::get(&board.player_1);
-							let mut score_card_2 = <AccountScoreCard<T>>::get(&board.player_2);
-							// emit that a user has won
-							match winner {
-								Player::One => {
-									score_card_1.won = score_card_1.won + 1;
-									score_card_2.lost = score_card_2.lost + 1;
-									score_card_1.points += T::PointsForWin::get() as i64;
-									score_card_2.points -= T::PointsForLoss::get() as i64;
-									Self::deposit_event(Event::GameWon(
-										board.player_1.clone(),
-										board_id,
-									));
-								},
-								Player::Two => {
-									score_card_1.lost = score_card_1.lost + 1;
-									score_card_2.won = score_card_2.won + 1;
-									score_card_1.points -= T::PointsForLoss::get() as i64;
-									score_card_2.points += T::PointsForWin::get() as i64;
-									Self::deposit_event(Event::GameWon(
-										board.player_2.clone(),
-										board_id,
-									));
-								},
-							};
-							// update the score cards
-							<AccountScoreCard<T>>::set(&board.player_1, score_card_1);
-							<AccountScoreCard<T>>::set(&board.player_2, score_card_2);
-							// emit that a game has ended.
-							Self::deposit_event(Event::GameEnded(board_id));
-							return Ok(());
-						},
-						WinState::Draw => (),
-						WinState::Ongoing => (),
-					}
+			if Self::flag_has_fallen(&board, &player) {
+				Self::lose_on_time(board_id, board)?;
+				return Ok(())
+			}
+			board.play(player.clone(), column as usize).map_err(<Error<T>>::from)?;
+			// emit event about the move
+			Self::deposit_event(Event::MoveMade(player, other_player));
+			Self::finish_move(board_id, board, column, MoveKind::Drop)?;
+			Ok(())
+		}
 
-					if !board.is_playable() {
-						// game is not playable.
-						// it's a draw
-						board.active = false;
-						let mut score_card_1 = <AccountScoreCard<T>>::get(&board.player_1);
-						score_card_1.draw += 1;
-						score_card_1.points += T::PointsForDraw::get() as i64;
-						let mut score_card_2 = <AccountScoreCard<T>>::get(&board.player_2);
-						score_card_2.draw += 1;
-						score_card_2.points += T::PointsForDraw::get() as i64;
-						<ConnectFourBoardById<T>>::insert(&board_id, board);
-						// remove board from users' storagedoublemap
-						<Challenges<T>>::remove(&player, &other_player);
-						<Challenges<T>>::remove(&other_player, &player);
-						Self::deposit_event(Event::GameEnded(board_id));
-						Self::deposit_event(Event::GameDrawn(board_id));
-					} else {
-						<ConnectFourBoardById<T>>::insert(&board_id, board);
-						Self::deposit_event(Event::MoveMade(player, other_player));
+		/// pop one of your own coins out of the bottom of a column, in a PopOut game.
+		#[pallet::weight(10_000 + Pallet::<T>::game_end_weight())]
+		pub fn pop(origin: OriginFor<T>, board_id: H256, column: u32) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			ensure!(
+				board.player_1 == player || board.player_2 == player,
+				<Error<T>>::NotYourBoard
+			);
+			ensure!(board.challenge_accepted, <Error<T>>::ChallengeNotYetAccepted);
+			ensure!(board.active, <Error<T>>::GameEnded);
+			if Self::flag_has_fallen(&board, &player) {
+				Self::lose_on_time(board_id, board)?;
+				return Ok(())
+			}
+			board.pop(player.clone(), column as usize).map_err(<Error<T>>::from)?;
+			Self::deposit_event(Event::CoinPopped(player, board_id));
+			Self::finish_move(board_id, board, column, MoveKind::Pop)?;
+			Ok(())
+		}
+
+		/// Resign from a game, conceding the win to the opponent.
+		#[pallet::weight(10_000 + Pallet::<T>::game_end_weight())]
+		pub fn resign(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			let winner = if player == board.player_1 {
+				Player::Two
+			} else if player == board.player_2 {
+				Player::One
+			} else {
+				return Err(<Error<T>>::NotYourBoard.into())
+			};
+			ensure!(board.challenge_accepted, <Error<T>>::ChallengeNotYetAccepted);
+			ensure!(board.active, <Error<T>>::GameEnded);
+			board.end_game(WinState::Player(winner));
+			Self::deposit_event(Event::PlayerResigned(player, board_id));
+			Self::conclude_game(board_id, board)?;
+			Ok(())
+		}
+
+		/// Offer your opponent a draw. The offer lapses when the next move is made.
+		#[pallet::weight(10_000)]
+		pub fn offer_draw(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			let offered_by = Self::player_of(&board, &player)?;
+			ensure!(board.challenge_accepted, <Error<T>>::ChallengeNotYetAccepted);
+			ensure!(board.active, <Error<T>>::GameEnded);
+			ensure!(board.draw_offer.is_none(), <Error<T>>::DrawAlreadyOffered);
+			board.draw_offer = Some(offered_by);
+			<ConnectFourBoardById<T>>::insert(&board_id, board);
+			Self::deposit_event(Event::DrawOffered(player, board_id));
+			Ok(())
+		}
+
+		/// Accept the draw your opponent offered, ending the game.
+		#[pallet::weight(10_000 + Pallet::<T>::game_end_weight())]
+		pub fn accept_draw(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			let answered_by = Self::player_of(&board, &player)?;
+			ensure!(board.active, <Error<T>>::GameEnded);
+			let offered_by = board.draw_offer.ok_or(<Error<T>>::NoDrawOffer)?;
+			ensure!(offered_by != answered_by, <Error<T>>::CannotAnswerYourOwnDrawOffer);
+			board.end_game(WinState::Draw);
+			Self::conclude_game(board_id, board)?;
+			Ok(())
+		}
+
+		/// Decline the draw your opponent offered, the game goes on.
+		#[pallet::weight(10_000)]
+		pub fn decline_draw(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			let answered_by = Self::player_of(&board, &player)?;
+			ensure!(board.active, <Error<T>>::GameEnded);
+			let offered_by = board.draw_offer.ok_or(<Error<T>>::NoDrawOffer)?;
+			ensure!(offered_by != answered_by, <Error<T>>::CannotAnswerYourOwnDrawOffer);
+			board.draw_offer = None;
+			<ConnectFourBoardById<T>>::insert(&board_id, board);
+			Self::deposit_event(Event::DrawDeclined(player, board_id));
+			Ok(())
+		}
+
+		/// Ask your opponent to let you take back your last move. The request lapses when
+		/// your opponent moves instead.
+		#[pallet::weight(10_000)]
+		pub fn request_takeback(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			let requested_by = Self::player_of(&board, &player)?;
+			ensure!(board.challenge_accepted, <Error<T>>::ChallengeNotYetAccepted);
+			ensure!(board.active, <Error<T>>::GameEnded);
+			ensure!(
+				!board.settings.rated || T::TakebacksInRatedGames::get(),
+				<Error<T>>::TakebacksNotAllowed
+			);
+			ensure!(board.last_played.as_ref() == Some(&player), <Error<T>>::NotYourLastMove);
+			// swapping sides takes over the opening coin, it can't be taken back.
+			ensure!(
+				!board.swapped || Self::get_move_history(&board_id).len() > 1,
+				<Error<T>>::NotYourLastMove
+			);
+			ensure!(board.takeback_request.is_none(), <Error<T>>::TakebackAlreadyRequested);
+			board.takeback_request = Some(requested_by);
+			<ConnectFourBoardById<T>>::insert(&board_id, board);
+			Self::deposit_event(Event::TakebackRequested(player, board_id));
+			Ok(())
+		}
+
+		/// Let your opponent take back their last move, it is their turn again.
+		#[pallet::weight(10_000 + Pallet::<T>::enqueue_weight())]
+		pub fn accept_takeback(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			let accepted_by = Self::player_of(&board, &player)?;
+			ensure!(board.active, <Error<T>>::GameEnded);
+			let requested_by = board.takeback_request.ok_or(<Error<T>>::NoTakebackRequest)?;
+			ensure!(requested_by != accepted_by, <Error<T>>::CannotAcceptYourOwnTakeback);
+			Self::take_back_move(board_id, &mut board)?;
+			// the clock starts again for the move that was taken back.
+			board.last_move_at = Self::current_block();
+			Self::schedule_move_deadline(board_id, &board)?;
+			<ConnectFourBoardById<T>>::insert(&board_id, board);
+			Self::deposit_event(Event::TakebackAccepted(player, board_id));
+			Ok(())
+		}
+
+		/// Claim the win of a game played on a clock, when your opponent is to move and has
+		/// run out of time.
+		#[pallet::weight(10_000 + Pallet::<T>::game_end_weight())]
+		pub fn claim_timeout(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			let claimed_by = Self::player_of(&board, &player)?;
+			ensure!(board.challenge_accepted, <Error<T>>::ChallengeNotYetAccepted);
+			ensure!(board.active, <Error<T>>::GameEnded);
+			ensure!(board.clock.is_some(), <Error<T>>::NoTimeControl);
+			ensure!(
+				board.player_to_move() != claimed_by && Self::out_of_time(&board),
+				<Error<T>>::OpponentHasTimeLeft
+			);
+			Self::lose_on_time(board_id, board)?;
+			Ok(())
+		}
+
+		/// Take the opening coin of your opponent as your own and swap sides, instead of
+		/// making your first move, in a game played with the swap rule. Your opponent moves
+		/// next.
+		#[pallet::weight(10_000 + Pallet::<T>::game_end_weight())]
+		pub fn swap_sides(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			let swapped_by = Self::player_of(&board, &player)?;
+			ensure!(board.challenge_accepted, <Error<T>>::ChallengeNotYetAccepted);
+			ensure!(board.active, <Error<T>>::GameEnded);
+			ensure!(board.settings.swap_rule, <Error<T>>::SwapRuleNotInPlay);
+			if Self::flag_has_fallen(&board, &player) {
+				Self::lose_on_time(board_id, board)?;
+				return Ok(())
+			}
+			ensure!(
+				!board.swapped &&
+					board.player_to_move() == swapped_by &&
+					Self::get_move_history(&board_id).len() == 1,
+				<Error<T>>::CannotSwapSides
+			);
+			board.swap_sides();
+			// the swap takes the place of a move on the clock.
+			let spent = Self::current_block().saturating_sub(board.last_move_at);
+			if let (Some(clock), Some(time_control)) =
+				(board.clock.as_mut(), board.settings.time_control)
+			{
+				clock.charge(board.first_player, spent, time_control.increment);
+			}
+			board.last_move_at = Self::current_block();
+			Self::schedule_move_deadline(board_id, &board)?;
+			// the bets follow the players.
+			if <Bets<T>>::contains_key(&board_id) {
+				<Bets<T>>::mutate(&board_id, |bets| {
+					for bet in bets.iter_mut() {
+						bet.outcome = bet.outcome.swapped();
 					}
-					return Ok(());
-				},
+				});
+			}
+			<ConnectFourBoardById<T>>::insert(&board_id, board);
+			Self::deposit_event(Event::SidesSwapped(player, board_id));
+			Ok(())
+		}
+
+		/// Remove a game that ended at least `PruneAfter` blocks ago from storage. Anyone can
+		/// reap a game, and the deposit reserved for it is paid back to the challenger.
+		#[pallet::weight(10_000)]
+		pub fn reap_game(origin: OriginFor<T>, board_id: H256) -> DispatchResult {
+			ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			ensure!(board.challenge_accepted && !board.active, <Error<T>>::GameNotFinished);
+			ensure!(
+				board.last_move_at.saturating_add(T::PruneAfter::get()) <= Self::current_block(),
+				<Error<T>>::GameTooRecent
+			);
+			Self::prune_board(board_id);
+			Ok(())
+		}
+
+		/// Create a single-elimination tournament for up to `max_players` players, whose
+		/// games are played with the given settings.
+		#[pallet::weight(10_000)]
+		pub fn create_tournament(
+			origin: OriginFor<T>,
+			settings: GameSettings,
+			max_players: u32,
+		) -> DispatchResult {
+			let creator = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			ensure!(
+				settings.is_valid(T::MaxRows::get(), T::MaxColumns::get()),
+				<Error<T>>::InvalidGameSettings
+			);
+			ensure!(settings.suits_competitions(), <Error<T>>::InvalidCompetitionSettings);
+			ensure!(
+				(2..=T::MaxTournamentPlayers::get()).contains(&max_players),
+				<Error<T>>::InvalidTournamentSize
+			);
+			let tournament_id = Self::new_competition_id(&creator)?;
+			let tournament = Tournament {
+				creator: creator.clone(),
+				settings,
+				max_players,
+				players: BoundedVec::default(),
+				status: TournamentStatus::Registration,
+				round: 0,
+				matches: BoundedVec::default(),
 			};
+			<Tournaments<T>>::insert(&tournament_id, tournament);
+			Self::deposit_event(Event::TournamentCreated(creator, tournament_id));
+			Ok(())
+		}
+
+		/// Register for a tournament that hasn't started yet.
+		#[pallet::weight(10_000)]
+		pub fn join_tournament(origin: OriginFor<T>, tournament_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut tournament =
+				Self::get_tournament(&tournament_id).ok_or(<Error<T>>::TournamentDoesNotExist)?;
+			ensure!(
+				tournament.status == TournamentStatus::Registration,
+				<Error<T>>::TournamentRegistrationClosed
+			);
+			ensure!(!tournament.players.contains(&player), <Error<T>>::AlreadyRegistered);
+			ensure!(
+				(tournament.players.len() as u32) < tournament.max_players,
+				<Error<T>>::TournamentFull
+			);
+			tournament.players.try_push(player.clone()).map_err(|_| <Error<T>>::TournamentFull)?;
+			<Tournaments<T>>::insert(&tournament_id, tournament);
+			Self::deposit_event(Event::TournamentJoined(player, tournament_id));
+			Ok(())
+		}
+
+		/// Close the registration of your tournament and start its first round. Players are
+		/// seeded by rating, and the top seeds get the byes when the number of players isn't
+		/// a power of two.
+		#[pallet::weight(
+			10_000 + Pallet::<T>::game_creation_weight(T::MaxTournamentPlayers::get() / 2)
+		)]
+		pub fn start_tournament(origin: OriginFor<T>, tournament_id: H256) -> DispatchResult {
+			let creator = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut tournament =
+				Self::get_tournament(&tournament_id).ok_or(<Error<T>>::TournamentDoesNotExist)?;
+			ensure!(tournament.creator == creator, <Error<T>>::NotYourTournament);
+			ensure!(
+				tournament.status == TournamentStatus::Registration,
+				<Error<T>>::TournamentRegistrationClosed
+			);
+			ensure!(tournament.players.len() >= 2, <Error<T>>::NotEnoughPlayers);
+			// the sort is stable, so equally rated players are seeded in order of registration.
+			tournament.players[..].sort_by_key(|player| Reverse(Self::get_rating(player)));
+			let slots = bracket_order(tournament.players.len().next_power_of_two())
+				.into_iter()
+				.map(|seed| tournament.players.get(seed).cloned())
+				.collect();
+			tournament.status = TournamentStatus::Running;
+			tournament.round = 1;
+			Self::pair_tournament_round(tournament_id, &mut tournament, slots)?;
+			<Tournaments<T>>::insert(&tournament_id, tournament);
+			Ok(())
+		}
+
+		/// Start the next round of a tournament, once every match of its current round has a
+		/// winner. Anyone can start it.
+		#[pallet::weight(
+			10_000 + Pallet::<T>::game_creation_weight(T::MaxTournamentPlayers::get() / 2)
+		)]
+		pub fn start_tournament_round(origin: OriginFor<T>, tournament_id: H256) -> DispatchResult {
+			ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut tournament =
+				Self::get_tournament(&tournament_id).ok_or(<Error<T>>::TournamentDoesNotExist)?;
+			ensure!(
+				tournament.status == TournamentStatus::Running,
+				<Error<T>>::TournamentNotRunning
+			);
+			ensure!(
+				tournament.matches.iter().all(|m| m.winner.is_some()),
+				<Error<T>>::RoundNotFinished
+			);
+			let slots = tournament.matches.iter().map(|m| m.winner.clone()).collect();
+			tournament.round += 1;
+			Self::pair_tournament_round(tournament_id, &mut tournament, slots)?;
+			<Tournaments<T>>::insert(&tournament_id, tournament);
+			Ok(())
+		}
+
+		/// Create a round-robin league for up to `max_players` players, whose games are
+		/// played with the given settings.
+		#[pallet::weight(10_000)]
+		pub fn create_league(
+			origin: OriginFor<T>,
+			settings: GameSettings,
+			max_players: u32,
+		) -> DispatchResult {
+			let creator = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			ensure!(
+				settings.is_valid(T::MaxRows::get(), T::MaxColumns::get()),
+				<Error<T>>::InvalidGameSettings
+			);
+			ensure!(settings.suits_competitions(), <Error<T>>::InvalidCompetitionSettings);
+			ensure!(
+				(2..=T::MaxLeaguePlayers::get()).contains(&max_players),
+				<Error<T>>::InvalidLeagueSize
+			);
+			let league_id = Self::new_competition_id(&creator)?;
+			let league = League {
+				creator: creator.clone(),
+				settings,
+				max_players,
+				players: BoundedVec::default(),
+				status: TournamentStatus::Registration,
+				ends_at: 0,
+				round: 0,
+				fixtures: BoundedVec::default(),
+				ranking: BoundedVec::default(),
+			};
+			<Leagues<T>>::insert(&league_id, league);
+			Self::deposit_event(Event::LeagueCreated(creator, league_id));
+			Ok(())
+		}
+
+		/// Register for a league whose season hasn't started yet.
+		#[pallet::weight(10_000)]
+		pub fn join_league(origin: OriginFor<T>, league_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut league = Self::get_league(&league_id).ok_or(<Error<T>>::LeagueDoesNotExist)?;
+			ensure!(
+				league.status == TournamentStatus::Registration,
+				<Error<T>>::LeagueRegistrationClosed
+			);
+			ensure!(!league.players.contains(&player), <Error<T>>::AlreadyRegistered);
+			ensure!((league.players.len() as u32) < league.max_players, <Error<T>>::LeagueFull);
+			league.players.try_push(player.clone()).map_err(|_| <Error<T>>::LeagueFull)?;
+			<Leagues<T>>::insert(&league_id, league);
+			Self::deposit_event(Event::LeagueJoined(player, league_id));
+			Ok(())
+		}
+
+		/// Close the registration of your league and start its season with the games of the
+		/// first round: every player plays every other player twice over the season, moving
+		/// first in one of the two games. The season closes `LeagueSeasonLength` blocks later.
+		#[pallet::weight(
+			10_000 +
+				Pallet::<T>::game_creation_weight(T::MaxLeaguePlayers::get() / 2) +
+				Pallet::<T>::enqueue_weight()
+		)]
+		pub fn start_league(origin: OriginFor<T>, league_id: H256) -> DispatchResult {
+			let creator = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut league = Self::get_league(&league_id).ok_or(<Error<T>>::LeagueDoesNotExist)?;
+			ensure!(league.creator == creator, <Error<T>>::NotYourLeague);
+			ensure!(
+				league.status == TournamentStatus::Registration,
+				<Error<T>>::LeagueRegistrationClosed
+			);
+			ensure!(league.players.len() >= 2, <Error<T>>::NotEnoughPlayers);
+			league.status = TournamentStatus::Running;
+			league.round = 1;
+			Self::create_league_fixtures(league_id, &mut league)?;
+			league.ends_at = Self::enqueue::<LeagueEnds<T>>(
+				Self::current_block().saturating_add(T::LeagueSeasonLength::get()),
+				league_id,
+			)?;
+			Self::deposit_event(Event::LeagueStarted(league_id, league.ends_at));
+			<Leagues<T>>::insert(&league_id, league);
+			Ok(())
+		}
+
+		/// Start the next round of a league whose season hasn't closed, once every game of
+		/// the rounds before it has ended. Anyone can start it.
+		#[pallet::weight(
+			10_000 + Pallet::<T>::game_creation_weight(T::MaxLeaguePlayers::get() / 2)
+		)]
+		pub fn start_league_round(origin: OriginFor<T>, league_id: H256) -> DispatchResult {
+			ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut league = Self::get_league(&league_id).ok_or(<Error<T>>::LeagueDoesNotExist)?;
+			ensure!(league.status == TournamentStatus::Running, <Error<T>>::LeagueNotRunning);
+			ensure!(
+				league.round < league_rounds(league.players.len()),
+				<Error<T>>::NoRoundsLeft
+			);
+			ensure!(
+				league.fixtures.iter().all(|fixture| fixture.result != WinState::Ongoing),
+				<Error<T>>::RoundNotFinished
+			);
+			league.round += 1;
+			Self::create_league_fixtures(league_id, &mut league)?;
+			<Leagues<T>>::insert(&league_id, league);
+			Ok(())
+		}
+
+		/// Create a Swiss tournament of `rounds` rounds, for up to `max_players` players,
+		/// whose games are played with the given settings.
+		#[pallet::weight(10_000)]
+		pub fn create_swiss_tournament(
+			origin: OriginFor<T>,
+			settings: GameSettings,
+			max_players: u32,
+			rounds: u32,
+		) -> DispatchResult {
+			let creator = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			ensure!(
+				settings.is_valid(T::MaxRows::get(), T::MaxColumns::get()),
+				<Error<T>>::InvalidGameSettings
+			);
+			ensure!(settings.suits_competitions(), <Error<T>>::InvalidCompetitionSettings);
+			ensure!(
+				(2..=T::MaxTournamentPlayers::get()).contains(&max_players),
+				<Error<T>>::InvalidTournamentSize
+			);
+			ensure!(rounds >= 1 && rounds < max_players, <Error<T>>::InvalidSwissRounds);
+			let tournament_id = Self::new_competition_id(&creator)?;
+			let tournament = SwissTournament {
+				creator: creator.clone(),
+				settings,
+				max_players,
+				rounds,
+				players: BoundedVec::default(),
+				status: TournamentStatus::Registration,
+				round: 0,
+				games: BoundedVec::default(),
+				ranking: BoundedVec::default(),
+			};
+			<SwissTournaments<T>>::insert(&tournament_id, tournament);
+			Self::deposit_event(Event::SwissTournamentCreated(creator, tournament_id));
+			Ok(())
+		}
+
+		/// Register for a Swiss tournament that hasn't started yet.
+		#[pallet::weight(10_000)]
+		pub fn join_swiss_tournament(origin: OriginFor<T>, tournament_id: H256) -> DispatchResult {
+			let player = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut tournament = Self::get_swiss_tournament(&tournament_id)
+				.ok_or(<Error<T>>::SwissTournamentDoesNotExist)?;
+			ensure!(
+				tournament.status == TournamentStatus::Registration,
+				<Error<T>>::TournamentRegistrationClosed
+			);
+			ensure!(
+				!tournament.players.iter().any(|registered| registered.account == player),
+				<Error<T>>::AlreadyRegistered
+			);
+			ensure!(
+				(tournament.players.len() as u32) < tournament.max_players,
+				<Error<T>>::TournamentFull
+			);
+			let swiss_player = SwissPlayer {
+				account: player.clone(),
+				score: 0,
+				opponents: BoundedVec::default(),
+				colour_balance: 0,
+				had_bye: false,
+			};
+			tournament.players.try_push(swiss_player).map_err(|_| <Error<T>>::TournamentFull)?;
+			<SwissTournaments<T>>::insert(&tournament_id, tournament);
+			Self::deposit_event(Event::SwissTournamentJoined(player, tournament_id));
+			Ok(())
+		}
+
+		/// Close the registration of your Swiss tournament and start its first round.
+		/// Players are seeded by rating.
+		#[pallet::weight(
+			10_000 +
+				Pallet::<T>::game_creation_weight(T::MaxTournamentPlayers::get() / 2) +
+				Pallet::<T>::pairing_weight()
+		)]
+		pub fn start_swiss_tournament(origin: OriginFor<T>, tournament_id: H256) -> DispatchResult {
+			let creator = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut tournament = Self::get_swiss_tournament(&tournament_id)
+				.ok_or(<Error<T>>::SwissTournamentDoesNotExist)?;
+			ensure!(tournament.creator == creator, <Error<T>>::NotYourTournament);
+			ensure!(
+				tournament.status == TournamentStatus::Registration,
+				<Error<T>>::TournamentRegistrationClosed
+			);
+			ensure!(tournament.players.len() >= 2, <Error<T>>::NotEnoughPlayers);
+			ensure!(
+				(tournament.rounds as usize) < tournament.players.len(),
+				<Error<T>>::InvalidSwissRounds
+			);
+			// the sort is stable, so equally rated players are seeded in order of registration.
+			tournament.players[..].sort_by_key(|player| Reverse(Self::get_rating(&player.account)));
+			tournament.status = TournamentStatus::Running;
+			tournament.round = 1;
+			Self::pair_swiss_round(tournament_id, &mut tournament)?;
+			<SwissTournaments<T>>::insert(&tournament_id, tournament);
+			Ok(())
+		}
+
+		/// Pair the next round of a Swiss tournament, once every game of its current round
+		/// has ended. Anyone can pair it.
+		#[pallet::weight(
+			10_000 +
+				Pallet::<T>::game_creation_weight(T::MaxTournamentPlayers::get() / 2) +
+				Pallet::<T>::pairing_weight()
+		)]
+		pub fn start_swiss_round(origin: OriginFor<T>, tournament_id: H256) -> DispatchResult {
+			ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let mut tournament = Self::get_swiss_tournament(&tournament_id)
+				.ok_or(<Error<T>>::SwissTournamentDoesNotExist)?;
+			ensure!(
+				tournament.status == TournamentStatus::Running,
+				<Error<T>>::TournamentNotRunning
+			);
+			ensure!(
+				tournament.games.iter().all(|game| game.result != WinState::Ongoing),
+				<Error<T>>::RoundNotFinished
+			);
+			tournament.round += 1;
+			Self::pair_swiss_round(tournament_id, &mut tournament)?;
+			<SwissTournaments<T>>::insert(&tournament_id, tournament);
+			Ok(())
+		}
+
+		/// Bet `amount` on the result of a game played by others: a win for either player,
+		/// or a draw. The bet is reserved until the game ends, and bets close once
+		/// `BettingCutoff` moves have been played, even if some are taken back.
+		#[pallet::weight(10_000)]
+		pub fn place_bet(
+			origin: OriginFor<T>,
+			board_id: H256,
+			outcome: WinState,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let bettor = ensure_signed(origin)?;
+			Self::ensure_migrated()?;
+			let board =
+				Self::get_connect_four_board_by_id(&board_id).ok_or(<Error<T>>::GameDoesNotExist)?;
+			ensure!(board.challenge_accepted, <Error<T>>::ChallengeNotYetAccepted);
+			ensure!(board.active, <Error<T>>::GameEnded);
+			ensure!(Self::player_of(&board, &bettor).is_err(), <Error<T>>::CannotBetOnYourOwnGame);
+			ensure!(outcome != WinState::Ongoing, <Error<T>>::InvalidBetOutcome);
+			ensure!(amount >= T::MinimumBet::get(), <Error<T>>::BetTooSmall);
+			ensure!(board.moves_played < T::BettingCutoff::get(), <Error<T>>::BettingClosed);
+			<Bets<T>>::try_mutate(&board_id, |bets| {
+				bets.try_push(Bet { bettor: bettor.clone(), outcome, amount })
+					.map_err(|_| <Error<T>>::TooManyBets)
+			})?;
+			T::Currency::reserve(&bettor, amount).map_err(|_| <Error<T>>::CannotAffordBet)?;
+			Self::deposit_event(Event::BetPlaced(bettor, board_id, outcome, amount));
 			Ok(())
 		}
 	}
@@ -683,4 +2411,1207 @@ impl<T: Config> Pallet<T> {
 		Nonce::<T>::put(nonce.wrapping_add(1));
 		nonce.encode()
 	}
+
+	/// Derives the ID of a new board from its players, the nonce and the current block, so
+	/// that boards created in the same block get different IDs. Open challenges have no
+	/// opponent yet. Fails if a board or an open challenge already has the ID.
+	pub fn new_board_id(
+		player_1: &T::AccountId,
+		player_2: Option<&T::AccountId>,
+	) -> Result<H256, Error<T>> {
+		let nonce = Nonce::<T>::get();
+		Nonce::<T>::put(nonce.wrapping_add(1));
+		let subject = (player_1, player_2, nonce, Self::current_block());
+		let board_id = H256::from(sp_io::hashing::blake2_256(&subject.encode()));
+		ensure!(
+			!<ConnectFourBoardById<T>>::contains_key(&board_id) &&
+				!<Lobby<T>>::contains_key(&board_id),
+			<Error<T>>::BoardIdTaken
+		);
+		Ok(board_id)
+	}
+
+	/// Derives the ID of a new tournament, league or Swiss tournament from its creator, the
+	/// nonce and the current block, like `new_board_id`. Fails if a tournament, a league or
+	/// a Swiss tournament already has the ID.
+	fn new_competition_id(creator: &T::AccountId) -> Result<H256, Error<T>> {
+		let nonce = Nonce::<T>::get();
+		Nonce::<T>::put(nonce.wrapping_add(1));
+		let subject = (creator, nonce, Self::current_block());
+		let id = H256::from(sp_io::hashing::blake2_256(&subject.encode()));
+		ensure!(
+			!<Tournaments<T>>::contains_key(&id) &&
+				!<Leagues<T>>::contains_key(&id) &&
+				!<SwissTournaments<T>>::contains_key(&id),
+			<Error<T>>::CompetitionIdTaken
+		);
+		Ok(id)
+	}
+
+	/// Adds a new board to the map of all boards, with the next `GameIndex`.
+	fn index_board(board_id: H256) {
+		let index = <GameCount<T>>::get();
+		<ConnectFourBoards<T>>::insert(&board_id, index);
+		<GameIndex<T>>::insert(index, board_id);
+		<GameCount<T>>::put(index.saturating_add(1));
+	}
+
+	/// Removes a board from the map of all boards, along with its `GameIndex`.
+	fn unindex_board(board_id: H256) {
+		if let Some(index) = <ConnectFourBoards<T>>::take(&board_id) {
+			<GameIndex<T>>::remove(index);
+		}
+	}
+
+	/// The boards with a `GameIndex` from `start`, up to `limit` indices, leaving out the
+	/// boards that have been removed. Front-ends can page through all the boards this way.
+	pub fn board_ids(start: u64, limit: u32) -> Vec<H256> {
+		let end = start.saturating_add(limit as u64).min(<GameCount<T>>::get());
+		(start..end).filter_map(<GameIndex<T>>::get).collect()
+	}
+
+	/// Reserves the `GameDeposit` for a new board from `account`.
+	fn reserve_deposit(account: &T::AccountId, board_id: H256) -> Result<(), Error<T>> {
+		let deposit = T::GameDeposit::get();
+		if deposit.is_zero() {
+			return Ok(())
+		}
+		T::Currency::reserve(account, deposit).map_err(|_| <Error<T>>::CannotAffordDeposit)?;
+		<Deposits<T>>::insert(&board_id, (account.clone(), deposit));
+		Ok(())
+	}
+
+	/// Pays back the deposit reserved for a board that is removed.
+	fn return_deposit(board_id: H256) {
+		if let Some((account, deposit)) = <Deposits<T>>::take(&board_id) {
+			T::Currency::unreserve(&account, deposit);
+		}
+	}
+
+	/// Removes a finished board and its move history from storage, and pays back its
+	/// deposit.
+	fn prune_board(board_id: H256) {
+		<ConnectFourBoardById<T>>::remove(&board_id);
+		<MoveHistory<T>>::remove(&board_id);
+		Self::unindex_board(board_id);
+		Self::return_deposit(board_id);
+		Self::deposit_event(Event::GamePruned(board_id));
+	}
+
+	/// Removes the finished boards queued in the `PruneQueue` up to block `now`, for as long
+	/// as the weight of the next board fits in `limit`. Boards that don't fit stay queued,
+	/// and are removed in a later block.
+	fn prune_finished_games(now: BlockNumber, limit: Weight) -> Weight {
+		let db_weight = T::DbWeight::get();
+		let block_weight = db_weight.reads_writes(1, 1);
+		let board_weight = db_weight.reads_writes(4, 7);
+		let mut weight = db_weight.reads_writes(1, 1);
+		if weight > limit {
+			return Weight::zero()
+		}
+		let mut block = <NextPruneBlock<T>>::get();
+		while block <= now &&
+			weight.saturating_add(block_weight).saturating_add(board_weight) <= limit
+		{
+			let mut queued = <PruneQueue<T>>::take(block).into_inner();
+			weight = weight.saturating_add(block_weight);
+			while weight.saturating_add(board_weight) <= limit {
+				let board_id = match queued.pop() {
+					Some(board_id) => board_id,
+					None => break,
+				};
+				weight = weight.saturating_add(board_weight);
+				// reaped boards have already been removed.
+				if <ConnectFourBoardById<T>>::contains_key(&board_id) {
+					Self::prune_board(board_id);
+				}
+			}
+			if !queued.is_empty() {
+				// what is left of the queue still fits in it.
+				<PruneQueue<T>>::insert(block, BlockQueue::<T>::truncate_from(queued));
+				break
+			}
+			block = block.saturating_add(1);
+		}
+		<NextPruneBlock<T>>::put(block);
+		weight
+	}
+
+	/// Which player `account` is on a board, if they play on it.
+	fn player_of(
+		board: &ConnectFourBoardStruct<T::AccountId>,
+		account: &T::AccountId,
+	) -> Result<Player, Error<T>> {
+		if *account == board.player_1 {
+			Ok(Player::One)
+		} else if *account == board.player_2 {
+			Ok(Player::Two)
+		} else {
+			Err(<Error<T>>::NotYourBoard)
+		}
+	}
+
+	/// Undoes the last move made on a board, by replaying the moves before it.
+	fn take_back_move(
+		board_id: H256,
+		board: &mut ConnectFourBoardStruct<T::AccountId>,
+	) -> Result<(), Error<T>> {
+		let mut moves = Self::get_move_history(&board_id).into_inner();
+		let taken_back = moves.pop().ok_or(<Error<T>>::NoTakebackRequest)?;
+		let replayed = ConnectFourBoardStruct::replay(board.settings, &moves)
+			.map_err(|_| <Error<T>>::BoardInvalid)?;
+		board.state = replayed.state;
+		board.last_played = replayed.last_played.map(|player| match player {
+			Player::One => board.player_1.clone(),
+			Player::Two => board.player_2.clone(),
+		});
+		// the time spent on the move, and the increment it got, are undone.
+		if taken_back.clock.is_some() {
+			board.clock = taken_back.clock;
+		}
+		board.draw_offer = None;
+		board.takeback_request = None;
+		let moves = BoundedVec::try_from(moves).map_err(|_| <Error<T>>::BoardInvalid)?;
+		<MoveHistory<T>>::insert(&board_id, moves);
+		Ok(())
+	}
+
+	/// Starts the game on a board whose challenge has been accepted.
+	fn start_game(
+		board_id: H256,
+		mut board: ConnectFourBoardStruct<T::AccountId>,
+	) -> Result<(), Error<T>> {
+		board.challenge_accepted = true;
+		board.create_game_board().map_err(|_| <Error<T>>::ActiveGameExists)?;
+		// the clock starts for the first move.
+		board.last_move_at = Self::current_block();
+		board.clock = board.settings.time_control.map(|time_control| Clock {
+			player_1: time_control.bank,
+			player_2: time_control.bank,
+		});
+		Self::schedule_move_deadline(board_id, &board)?;
+		let mut score_card_1 = Self::get_scorecard(&board.player_1);
+		let mut score_card_2 = Self::get_scorecard(&board.player_2);
+		score_card_1.played += 1;
+		score_card_2.played += 1;
+		<AccountScoreCard<T>>::set(&board.player_1, score_card_1);
+		<AccountScoreCard<T>>::set(&board.player_2, score_card_2);
+		<ConnectFourBoardById<T>>::insert(&board_id, board);
+		Ok(())
+	}
+
+	/// Creates a game between two accounts that starts right away, without a challenge. The
+	/// pairing decides who moves first, so `player_1` does: competitions are only created
+	/// with settings that leave it to the pairing, see `GameSettings::suits_competitions`.
+	fn create_game(
+		player_1: T::AccountId,
+		player_2: T::AccountId,
+		settings: GameSettings,
+	) -> Result<H256, Error<T>> {
+		let board_id = Self::new_board_id(&player_1, Some(&player_2))?;
+		let board = ConnectFourBoardStruct::<T::AccountId>::new(
+			board_id,
+			player_1.clone(),
+			player_2.clone(),
+			settings,
+		);
+		Self::start_game(board_id, board)?;
+		Self::index_board(board_id);
+		Self::link_board(&player_1, &player_2, board_id);
+		Self::deposit_event(Event::GameCreated(board_id));
+		Ok(board_id)
+	}
+
+	/// The player who moves first in a game with the given choice, drawing either player
+	/// for `FirstMove::Random`. The draw can be foreseen if the randomness source can.
+	fn choose_first_player(first_move: FirstMove) -> Player {
+		match first_move {
+			FirstMove::Challenger => Player::One,
+			FirstMove::Opponent => Player::Two,
+			FirstMove::Random => {
+				let subject = Self::encode_and_update_nonce();
+				let (random, _) = T::RandomnessSource::random(&subject);
+				if random.as_bytes()[0] % 2 == 0 {
+					Player::One
+				} else {
+					Player::Two
+				}
+			},
+		}
+	}
+
+	/// The weight of creating `games` games without a challenge, see `create_game`, and of
+	/// recording the competition each of them belongs to.
+	fn game_creation_weight(games: u32) -> Weight {
+		T::DbWeight::get()
+			.reads_writes(9, 12)
+			.saturating_add(Self::enqueue_weight())
+			.saturating_mul(games as Weight)
+	}
+
+	/// The weight of pairing a Swiss round, for the most steps the search takes, see
+	/// `pair_swiss_players`. A step looks through the players for an opponent, and checks
+	/// whether they have met against the opponents of the player.
+	fn pairing_weight() -> Weight {
+		let players = T::MaxTournamentPlayers::get() as Weight;
+		// about a nanosecond to compare two accounts.
+		let step = players.saturating_mul(players).saturating_mul(1_000);
+		step.saturating_mul(MAX_PAIRING_STEPS as Weight)
+	}
+
+	/// Fails while the boards stored before the last runtime upgrade are being migrated.
+	fn ensure_migrated() -> Result<(), Error<T>> {
+		ensure!(!migrations::v1::migrating::<T>(), <Error<T>>::MigrationInProgress);
+		Ok(())
+	}
+
+	/// Returns the current block number.
+	fn current_block() -> BlockNumber {
+		<frame_system::Pallet<T>>::block_number().unique_saturated_into()
+	}
+
+	/// Queues a board so that the player to move forfeits if they haven't moved
+	/// `MoveTimeout` blocks after the last move. Games with a time control are played on
+	/// their clock instead.
+	fn schedule_move_deadline(
+		board_id: H256,
+		board: &ConnectFourBoardStruct<T::AccountId>,
+	) -> Result<(), Error<T>> {
+		if board.settings.time_control.is_some() {
+			return Ok(())
+		}
+		let deadline = board.last_move_at.saturating_add(T::MoveTimeout::get());
+		Self::enqueue::<MoveDeadlines<T>>(deadline, board_id)?;
+		Ok(())
+	}
+
+	/// Queues `id` in `Queue` for block `block`, or for the first block after it whose queue
+	/// has room, and returns the block it was queued for. Fails if none of the
+	/// `MAX_QUEUE_SEARCH` blocks from `block` has room.
+	fn enqueue<Queue>(block: BlockNumber, id: H256) -> Result<BlockNumber, Error<T>>
+	where
+		Queue: frame_support::storage::StorageMap<
+			BlockNumber,
+			BlockQueue<T>,
+			Query = BlockQueue<T>,
+		>,
+	{
+		let last = block.saturating_add(MAX_QUEUE_SEARCH as BlockNumber - 1);
+		let mut block = block;
+		while Queue::try_mutate(block, |queue| queue.try_push(id)).is_err() {
+			ensure!(block < last, <Error<T>>::QueuesFull);
+			block = block.saturating_add(1);
+		}
+		Ok(block)
+	}
+
+	/// The weight of queueing an entry with `enqueue`, for the most blocks it looks through.
+	fn enqueue_weight() -> Weight {
+		T::DbWeight::get().reads_writes(MAX_QUEUE_SEARCH as Weight, 1)
+	}
+
+	/// Whether the player to move in a game played on a clock has run out of time.
+	fn out_of_time(board: &ConnectFourBoardStruct<T::AccountId>) -> bool {
+		let spent = Self::current_block().saturating_sub(board.last_move_at);
+		board.clock.map_or(false, |clock| spent > clock.time_left(board.player_to_move()))
+	}
+
+	/// Whether `player` is to move, and has run out of time.
+	fn flag_has_fallen(
+		board: &ConnectFourBoardStruct<T::AccountId>,
+		player: &T::AccountId,
+	) -> bool {
+		Self::player_of(board, player).ok() == Some(board.player_to_move()) &&
+			Self::out_of_time(board)
+	}
+
+	/// Ends a game lost by the player to move, who ran out of time.
+	fn lose_on_time(
+		board_id: H256,
+		mut board: ConnectFourBoardStruct<T::AccountId>,
+	) -> Result<(), Error<T>> {
+		let (loser, winner) = match board.player_to_move() {
+			Player::One => (board.player_1.clone(), Player::Two),
+			Player::Two => (board.player_2.clone(), Player::One),
+		};
+		board.end_game(WinState::Player(winner));
+		Self::conclude_game(board_id, board)?;
+		Self::deposit_event(Event::GameTimedOut(loser, board_id));
+		Ok(())
+	}
+
+	/// Records a move made on a board and stores the board, settling the game if the move
+	/// ended it.
+	fn finish_move(
+		board_id: H256,
+		mut board: ConnectFourBoardStruct<T::AccountId>,
+		column: u32,
+		kind: MoveKind,
+	) -> Result<(), Error<T>> {
+		let played = Move {
+			player: board.last_mover(),
+			column: column as u8,
+			block: Self::current_block(),
+			kind,
+			clock: board.clock,
+		};
+		// the mover's clock stops, and they get the increment.
+		let spent = played.block.saturating_sub(board.last_move_at);
+		if let (Some(clock), Some(time_control)) =
+			(board.clock.as_mut(), board.settings.time_control)
+		{
+			clock.charge(played.player, spent, time_control.increment);
+		}
+		board.moves_played = board.moves_played.saturating_add(1);
+		let history_full = <MoveHistory<T>>::mutate(&board_id, |moves| {
+			// the game is drawn once the history is full, so there is always room.
+			let _ = moves.try_push(played);
+			moves.len() as u32 >= T::MaxMoves::get()
+		});
+		// need to check if someone has won, or if the game is unplayable.
+		if history_full && board.get_winner() == WinState::Ongoing {
+			board.end_game(WinState::Draw);
+		}
+		match board.get_winner() {
+			WinState::Ongoing => {
+				// the clock starts for the opponent's move.
+				board.last_move_at = Self::current_block();
+				Self::schedule_move_deadline(board_id, &board)?;
+				<ConnectFourBoardById<T>>::insert(&board_id, board);
+				Ok(())
+			},
+			// the board has ended the game, settle the result.
+			_ => Self::conclude_game(board_id, board),
+		}
+	}
+
+	/// Forfeits the games whose player to move has run out of time by block `now`, for as
+	/// long as the weight of the next game fits in `limit`. Deadlines that don't fit stay
+	/// queued, and are checked in a later block.
+	fn expire_moves(now: BlockNumber, limit: Weight) -> Weight {
+		let db_weight = T::DbWeight::get();
+		let block_weight = db_weight.reads_writes(1, 1);
+		let board_weight = db_weight.reads_writes(3, 5).saturating_add(Self::game_end_weight());
+		let mut weight = db_weight.reads_writes(1, 1);
+		if weight > limit {
+			return Weight::zero()
+		}
+		let mut block = <NextDeadlineBlock<T>>::get();
+		while block <= now &&
+			weight.saturating_add(block_weight).saturating_add(board_weight) <= limit
+		{
+			let mut queued = <MoveDeadlines<T>>::take(block).into_inner();
+			weight = weight.saturating_add(block_weight);
+			while weight.saturating_add(board_weight) <= limit {
+				let board_id = match queued.pop() {
+					Some(board_id) => board_id,
+					None => break,
+				};
+				weight = weight.saturating_add(board_weight);
+				let board = match Self::get_connect_four_board_by_id(&board_id) {
+					Some(board) => board,
+					None => continue,
+				};
+				// the board has ended, or a move was made since this deadline was queued.
+				if !board.active ||
+					board.last_move_at.saturating_add(T::MoveTimeout::get()) > now
+				{
+					continue
+				}
+				// the game is ended in a later block, once the board can be queued.
+				if let Err(error) = Self::lose_on_time(board_id, board) {
+					log::warn!("Could not end the timed out game {:?}: {:?}", board_id, error);
+					queued.push(board_id);
+					break
+				}
+			}
+			if !queued.is_empty() {
+				// what is left of the queue still fits in it.
+				<MoveDeadlines<T>>::insert(block, BlockQueue::<T>::truncate_from(queued));
+				break
+			}
+			block = block.saturating_add(1);
+		}
+		<NextDeadlineBlock<T>>::put(block);
+		weight
+	}
+
+	/// Removes the open challenges that expire at block `now` from the lobby.
+	fn expire_open_challenges(now: BlockNumber) -> Weight {
+		let expiring = <LobbyExpiries<T>>::take(now);
+		let mut weight = T::DbWeight::get().reads_writes(1, 1);
+		for board_id in expiring {
+			// joined and cancelled challenges have already left the lobby.
+			if <Lobby<T>>::contains_key(&board_id) {
+				<Lobby<T>>::remove(&board_id);
+				Self::return_deposit(board_id);
+				Self::deposit_event(Event::OpenChallengeExpired(board_id));
+			}
+			weight = weight.saturating_add(T::DbWeight::get().reads_writes(2, 3));
+		}
+		weight
+	}
+
+	/// Replays a game from its moves, see `ConnectFourBoardStruct::replay`. A game that
+	/// reaches `MaxMoves` moves is drawn, like it is on chain.
+	pub fn replay(
+		settings: GameSettings,
+		moves: &[Move],
+	) -> Result<ConnectFourBoardStruct<Player>, GameError> {
+		let mut board = ConnectFourBoardStruct::replay(settings, moves)?;
+		if moves.len() as u32 >= T::MaxMoves::get() && board.get_winner() == WinState::Ongoing {
+			board.end_game(WinState::Draw);
+		}
+		Ok(board)
+	}
+
+	/// Updates the Elo ratings of both players of a game, given the score of player 1.
+	fn update_ratings(player_1: &T::AccountId, player_2: &T::AccountId, score_1: u32) {
+		let rating_1 = Self::get_rating(player_1);
+		let rating_2 = Self::get_rating(player_2);
+		let k_factor = T::RatingKFactor::get();
+		let new_rating_1 = new_rating(rating_1, rating_2, score_1, k_factor);
+		let new_rating_2 = new_rating(rating_2, rating_1, SCORE_SCALE - score_1, k_factor);
+		<AccountRating<T>>::insert(player_1, new_rating_1);
+		<AccountRating<T>>::insert(player_2, new_rating_2);
+		Self::deposit_event(Event::RatingChanged(player_1.clone(), rating_1, new_rating_1));
+		Self::deposit_event(Event::RatingChanged(player_2.clone(), rating_2, new_rating_2));
+	}
+
+	/// Lists a board under the pair of accounts playing on it. Challenges are checked
+	/// against `MaxGamesPerPair` before they are made, but tournament and league games
+	/// aren't: those beyond it are played without being listed.
+	fn link_board(player: &T::AccountId, other_player: &T::AccountId, board_id: H256) {
+		for (a, b) in [(player, other_player), (other_player, player)] {
+			if <Challenges<T>>::try_append(a, b, board_id).is_err() {
+				log::warn!("{:?} and {:?} have too many games to list {:?}", a, b, board_id);
+			}
+		}
+	}
+
+	/// Removes a board from the list of boards of the pair of accounts playing on it.
+	fn unlink_board(player: &T::AccountId, other_player: &T::AccountId, board_id: H256) {
+		for (a, b) in [(player, other_player), (other_player, player)] {
+			let mut boards = <Challenges<T>>::get(a, b);
+			boards.retain(|id| *id != board_id);
+			if boards.is_empty() {
+				<Challenges<T>>::remove(a, b);
+			} else {
+				<Challenges<T>>::insert(a, b, boards);
+			}
+		}
+	}
+
+	/// Removes a pending challenge between two accounts, along with its board. Nothing has
+	/// been staked or bet on a pending challenge yet.
+	fn remove_challenge(player: &T::AccountId, other_player: &T::AccountId, board_id: H256) {
+		Self::unlink_board(player, other_player, board_id);
+		<ConnectFourBoardById<T>>::remove(&board_id);
+		<Stakes<T>>::remove(&board_id);
+		Self::unindex_board(board_id);
+		Self::return_deposit(board_id);
+	}
+
+	/// Returns the bets placed on a game that has ended with a result nobody bet on.
+	fn refund_bets(board_id: H256) {
+		let bets = <Bets<T>>::take(&board_id);
+		if bets.is_empty() {
+			return
+		}
+		for bet in bets {
+			T::Currency::unreserve(&bet.bettor, bet.amount);
+		}
+		Self::deposit_event(Event::BetsRefunded(board_id));
+	}
+
+	/// Pays out the bets placed on a game that has ended. The bets on the result share the
+	/// pot, in proportion to their amounts: the losing bets are collected once, and every
+	/// winning bet gets its exact share of them, rounded down. The remainder is dust, less
+	/// than one unit for every winning bet, and goes to `HouseAccount`. When nobody bet on
+	/// the result, every bet is refunded.
+	fn settle_bets(board_id: H256, result: WinState) {
+		let (won, lost): (Vec<_>, Vec<_>) =
+			Self::get_bets(&board_id).into_iter().partition(|bet| bet.outcome == result);
+		let winning_pool = won
+			.iter()
+			.fold(BalanceOf::<T>::zero(), |pool, bet| pool.saturating_add(bet.amount));
+		if winning_pool.is_zero() {
+			return Self::refund_bets(board_id)
+		}
+		<Bets<T>>::remove(&board_id);
+		let mut losing_pool = NegativeImbalanceOf::<T>::zero();
+		for bet in lost.iter() {
+			let (stake, _) = T::Currency::slash_reserved(&bet.bettor, bet.amount);
+			losing_pool.subsume(stake);
+		}
+		let losing_total = losing_pool.peek();
+		for bet in won.iter() {
+			T::Currency::unreserve(&bet.bettor, bet.amount);
+			// the share is at most the losing total, which fits in a balance.
+			let share = multiply_by_rational_with_rounding(
+				bet.amount.unique_saturated_into(),
+				losing_total.unique_saturated_into(),
+				winning_pool.unique_saturated_into(),
+				Rounding::Down,
+			)
+			.map_or_else(Zero::zero, |share| share.unique_saturated_into());
+			let (winnings, rest) = losing_pool.split(share);
+			losing_pool = rest;
+			T::Currency::resolve_creating(&bet.bettor, winnings);
+		}
+		T::Currency::resolve_creating(&T::HouseAccount::get(), losing_pool);
+		let pot = winning_pool.saturating_add(losing_total);
+		Self::deposit_event(Event::BetsSettled(board_id, pot));
+	}
+
+	/// The weight of ending a game, on top of the call that ends it: queueing it for
+	/// pruning, settling the bets on it for the most bets a game can have, see
+	/// `settle_bets`, and replaying it when it is a drawn tournament game.
+	fn game_end_weight() -> Weight {
+		let per_bet = T::DbWeight::get().reads_writes(1, 1);
+		T::DbWeight::get()
+			.reads_writes(1, 1)
+			.saturating_add(Self::enqueue_weight())
+			.saturating_add(per_bet.saturating_mul(T::MaxBetsPerGame::get() as Weight))
+			.saturating_add(Self::game_creation_weight(1))
+	}
+
+	/// Pays out the pot of a staked game that has ended: the winner takes it, a draw
+	/// splits it, and the house fee goes to `HouseAccount`.
+	fn settle_stakes(board_id: H256, board: &ConnectFourBoardStruct<T::AccountId>) {
+		let stake = match <Stakes<T>>::take(&board_id) {
+			Some(stake) => stake,
+			None => return,
+		};
+		let players = [&board.player_1, &board.player_2];
+		// each player pays half of the fee on the pot.
+		let fee = T::HouseFee::get() * stake;
+		let house = T::HouseAccount::get();
+		for player in players {
+			// the part of the fee that can't be paid, when the house account doesn't exist,
+			// goes back to the player.
+			let unpaid = T::Currency::repatriate_reserved(player, &house, fee, BalanceStatus::Free)
+				.unwrap_or(fee);
+			T::Currency::unreserve(player, unpaid);
+		}
+		let remaining = stake.saturating_sub(fee);
+		match board.win_state {
+			WinState::Player(winner) => {
+				let (winner, loser) = match winner {
+					Player::One => (&board.player_1, &board.player_2),
+					Player::Two => (&board.player_2, &board.player_1),
+				};
+				let unpaid = T::Currency::repatriate_reserved(
+					loser,
+					winner,
+					remaining,
+					BalanceStatus::Free,
+				)
+				.unwrap_or(remaining);
+				T::Currency::unreserve(loser, unpaid);
+				T::Currency::unreserve(winner, remaining);
+				let pot = remaining.saturating_add(remaining);
+				Self::deposit_event(Event::PotWon(winner.clone(), board_id, pot));
+			},
+			_ => {
+				for player in players {
+					T::Currency::unreserve(player, remaining);
+				}
+				Self::deposit_event(Event::PotSplit(board_id, remaining));
+			},
+		}
+	}
+
+	/// Settles a board that has ended: frees the pair to play again, updates
+	/// both scorecards and announces the result. Nothing is changed if the board can't be
+	/// queued for pruning.
+	fn conclude_game(
+		board_id: H256,
+		mut board: ConnectFourBoardStruct<T::AccountId>,
+	) -> Result<(), Error<T>> {
+		// the board can be removed `PruneAfter` blocks from now.
+		board.last_move_at = Self::current_block();
+		let prune_at = board.last_move_at.saturating_add(T::PruneAfter::get());
+		Self::enqueue::<PruneQueue<T>>(prune_at, board_id)?;
+		// remove board from users' storagedoublemap
+		Self::unlink_board(&board.player_1, &board.player_2, board_id);
+		// get the scorecards.
+		let mut score_card_1 = <AccountScoreCard<T>>::get(&board.player_1);
+		let mut score_card_2 = <AccountScoreCard<T>>::get(&board.player_2);
+		// the score achieved by player 1, for the rating update.
+		let score_1 = match board.win_state {
+			WinState::Player(Player::One) => {
+				score_card_1.won += 1;
+				score_card_2.lost += 1;
+				score_card_1.points += T::PointsForWin::get() as i64;
+				score_card_2.points -= T::PointsForLoss::get() as i64;
+				Self::deposit_event(Event::GameWon(
+					board.player_1.clone(),
+					board_id,
+					board.winning_line.clone(),
+				));
+				SCORE_SCALE
+			},
+			WinState::Player(Player::Two) => {
+				score_card_1.lost += 1;
+				score_card_2.won += 1;
+				score_card_1.points -= T::PointsForLoss::get() as i64;
+				score_card_2.points += T::PointsForWin::get() as i64;
+				Self::deposit_event(Event::GameWon(
+					board.player_2.clone(),
+					board_id,
+					board.winning_line.clone(),
+				));
+				0
+			},
+			WinState::Draw => {
+				score_card_1.draw += 1;
+				score_card_1.points += T::PointsForDraw::get() as i64;
+				score_card_2.draw += 1;
+				score_card_2.points += T::PointsForDraw::get() as i64;
+				Self::deposit_event(Event::GameDrawn(board_id));
+				SCORE_SCALE / 2
+			},
+			WinState::Ongoing => {
+				log::warn!("Tried to conclude an ongoing game: {:?}", board_id);
+				return Ok(())
+			},
+		};
+		// update the score cards
+		<AccountScoreCard<T>>::set(&board.player_1, score_card_1);
+		<AccountScoreCard<T>>::set(&board.player_2, score_card_2);
+		if board.settings.rated {
+			Self::update_ratings(&board.player_1, &board.player_2, score_1);
+		}
+		Self::settle_stakes(board_id, &board);
+		Self::settle_bets(board_id, board.win_state);
+		<ConnectFourBoardById<T>>::insert(&board_id, &board);
+		// emit that a game has ended.
+		Self::deposit_event(Event::GameEnded(board_id));
+		Self::advance_tournament(board_id, &board);
+		Self::record_league_result(board_id, &board);
+		Self::record_swiss_result(board_id, &board);
+		Ok(())
+	}
+
+	/// Creates the games of a round of a Swiss tournament. The players are paired by score
+	/// with as few rematches as possible, see `pair_swiss_players`. With an odd number of
+	/// players, the lowest placed player who hasn't had a bye yet gets one. Of the two
+	/// players of a game, the one who has moved first less often moves first.
+	fn pair_swiss_round(
+		tournament_id: H256,
+		tournament: &mut SwissTournament<T>,
+	) -> Result<(), Error<T>> {
+		let players = &mut tournament.players;
+		// the sort is stable, so players level on score stay in seeding order.
+		let mut order: Vec<usize> = (0..players.len()).collect();
+		order.sort_by_key(|&index| Reverse(players[index].score));
+		if order.len() % 2 == 1 {
+			let bye = order
+				.iter()
+				.rposition(|&index| !players[index].had_bye)
+				.unwrap_or(order.len() - 1);
+			let index = order.remove(bye);
+			players[index].had_bye = true;
+			players[index].score += SWISS_WIN;
+		}
+		tournament.games = BoundedVec::default();
+		let pairs = pair_swiss_players(&order, |index, other| {
+			players[index].opponents.contains(&players[other].account)
+		});
+		for (index, other) in pairs {
+			let (first, second) = if players[other].colour_balance < players[index].colour_balance
+			{
+				(other, index)
+			} else {
+				(index, other)
+			};
+			let player_1 = players[first].account.clone();
+			let player_2 = players[second].account.clone();
+			let board_id =
+				Self::create_game(player_1.clone(), player_2.clone(), tournament.settings)?;
+			<SwissBoards<T>>::insert(&board_id, tournament_id);
+			// players meet fewer opponents than there are players, and there are fewer games
+			// than players.
+			players[first].colour_balance += 1;
+			players[first]
+				.opponents
+				.try_push(player_2.clone())
+				.map_err(|_| <Error<T>>::TournamentFull)?;
+			players[second].colour_balance -= 1;
+			players[second]
+				.opponents
+				.try_push(player_1.clone())
+				.map_err(|_| <Error<T>>::TournamentFull)?;
+			let game = SwissGame { player_1, player_2, board_id, result: WinState::Ongoing };
+			tournament.games.try_push(game).map_err(|_| <Error<T>>::TournamentFull)?;
+		}
+		Self::deposit_event(Event::SwissRoundStarted(tournament_id, tournament.round));
+		Ok(())
+	}
+
+	/// Records the result of a Swiss tournament game that has ended. Once every game of
+	/// the round has ended, the tournament finishes after its last round, and otherwise
+	/// anyone can pair the next round with `start_swiss_round`.
+	fn record_swiss_result(board_id: H256, board: &ConnectFourBoardStruct<T::AccountId>) {
+		let tournament_id = match <SwissBoards<T>>::take(&board_id) {
+			Some(tournament_id) => tournament_id,
+			None => return,
+		};
+		let mut tournament = match Self::get_swiss_tournament(&tournament_id) {
+			Some(tournament) => tournament,
+			None => return,
+		};
+		if let Some(game) = tournament.games.iter_mut().find(|game| game.board_id == board_id) {
+			game.result = board.result_for(&game.player_1);
+		}
+		let points = match board.win_state {
+			WinState::Player(Player::One) => [(&board.player_1, SWISS_WIN)].to_vec(),
+			WinState::Player(Player::Two) => [(&board.player_2, SWISS_WIN)].to_vec(),
+			_ => [(&board.player_1, SWISS_DRAW), (&board.player_2, SWISS_DRAW)].to_vec(),
+		};
+		for (account, points) in points {
+			if let Some(player) = tournament.players.iter_mut().find(|p| p.account == *account) {
+				player.score += points;
+			}
+		}
+		if tournament.games.iter().any(|game| game.result == WinState::Ongoing) {
+			<SwissTournaments<T>>::insert(&tournament_id, tournament);
+			return
+		}
+		if tournament.round >= tournament.rounds {
+			// the ranking has every player once.
+			tournament.ranking = BoundedVec::truncate_from(Self::swiss_ranking(&tournament));
+			tournament.status = TournamentStatus::Finished;
+			Self::deposit_event(Event::SwissTournamentFinished(
+				tournament_id,
+				tournament.ranking.to_vec(),
+			));
+		} else {
+			Self::deposit_event(Event::SwissRoundFinished(tournament_id, tournament.round));
+		}
+		<SwissTournaments<T>>::insert(&tournament_id, tournament);
+	}
+
+	/// The players of a Swiss tournament ranked by score, and then by their Buchholz score:
+	/// the sum of the scores of the players they met.
+	fn swiss_ranking(tournament: &SwissTournament<T>) -> Vec<T::AccountId> {
+		let score = |account: &T::AccountId| {
+			tournament
+				.players
+				.iter()
+				.find(|player| player.account == *account)
+				.map_or(0, |player| player.score)
+		};
+		let mut ranking: Vec<_> = tournament
+			.players
+			.iter()
+			.map(|player| {
+				let buchholz: u32 = player.opponents.iter().map(score).sum();
+				((Reverse(player.score), Reverse(buchholz)), player.account.clone())
+			})
+			.collect();
+		// the sort is stable, so players level on both scores stay in seeding order.
+		ranking.sort_by_key(|(key, _)| *key);
+		ranking.into_iter().map(|(_, player)| player).collect()
+	}
+
+	/// Creates the games of the current round of a league, see `league_round`.
+	fn create_league_fixtures(league_id: H256, league: &mut League<T>) -> Result<(), Error<T>> {
+		for (home, away) in league_round(league.players.len(), league.round) {
+			let home = league.players[home].clone();
+			let away = league.players[away].clone();
+			let board_id = Self::create_game(home.clone(), away.clone(), league.settings)?;
+			<LeagueBoards<T>>::insert(&board_id, league_id);
+			let fixture = LeagueFixture { home, away, board_id, result: WinState::Ongoing };
+			// a league has at most `MaxLeagueFixtures` fixtures over its rounds.
+			league.fixtures.try_push(fixture).map_err(|_| <Error<T>>::LeagueFull)?;
+		}
+		Self::deposit_event(Event::LeagueRoundStarted(league_id, league.round));
+		Ok(())
+	}
+
+	/// Records the result of a league game that has ended in the fixture list and in the
+	/// standings, unless the season has already closed.
+	fn record_league_result(board_id: H256, board: &ConnectFourBoardStruct<T::AccountId>) {
+		let league_id = match <LeagueBoards<T>>::take(&board_id) {
+			Some(league_id) => league_id,
+			None => return,
+		};
+		let mut league = match Self::get_league(&league_id) {
+			Some(league) if league.status == TournamentStatus::Running => league,
+			_ => return,
+		};
+		if let Some(fixture) = league.fixtures.iter_mut().find(|f| f.board_id == board_id) {
+			fixture.result = board.result_for(&fixture.home);
+		}
+		let mut standing_1 = Self::get_league_standing(&league_id, &board.player_1);
+		let mut standing_2 = Self::get_league_standing(&league_id, &board.player_2);
+		standing_1.played += 1;
+		standing_2.played += 1;
+		match board.win_state {
+			WinState::Player(Player::One) => {
+				standing_1.won += 1;
+				standing_2.lost += 1;
+				standing_1.points += T::PointsForWin::get() as i64;
+				standing_2.points -= T::PointsForLoss::get() as i64;
+			},
+			WinState::Player(Player::Two) => {
+				standing_1.lost += 1;
+				standing_2.won += 1;
+				standing_1.points -= T::PointsForLoss::get() as i64;
+				standing_2.points += T::PointsForWin::get() as i64;
+			},
+			_ => {
+				standing_1.draw += 1;
+				standing_2.draw += 1;
+				standing_1.points += T::PointsForDraw::get() as i64;
+				standing_2.points += T::PointsForDraw::get() as i64;
+			},
+		}
+		<LeagueStandings<T>>::insert(&league_id, &board.player_1, standing_1);
+		<LeagueStandings<T>>::insert(&league_id, &board.player_2, standing_2);
+		<Leagues<T>>::insert(&league_id, league);
+	}
+
+	/// The players of a league ranked by points. Players level on points are ranked by the
+	/// points they scored against each other, 2 for a win and 1 for a draw, and then by
+	/// their number of wins.
+	fn league_ranking(league_id: H256, league: &League<T>) -> Vec<T::AccountId> {
+		let standings: Vec<_> = league
+			.players
+			.iter()
+			.map(|player| (player.clone(), Self::get_league_standing(&league_id, player)))
+			.collect();
+		let points = |player: &T::AccountId| {
+			standings
+				.iter()
+				.find(|(other, _)| other == player)
+				.map_or(0, |(_, standing)| standing.points)
+		};
+		let head_to_head = |player: &T::AccountId| -> u32 {
+			let level = points(player);
+			league
+				.fixtures
+				.iter()
+				.filter_map(|fixture| {
+					let (own, opponent) = if fixture.home == *player {
+						(Player::One, &fixture.away)
+					} else if fixture.away == *player {
+						(Player::Two, &fixture.home)
+					} else {
+						return None
+					};
+					if points(opponent) != level {
+						return None
+					}
+					match fixture.result {
+						WinState::Player(winner) if winner == own => Some(2),
+						WinState::Draw => Some(1),
+						_ => None,
+					}
+				})
+				.sum()
+		};
+		let keys: Vec<_> = standings
+			.iter()
+			.map(|(player, standing)| {
+				(Reverse(standing.points), Reverse(head_to_head(player)), Reverse(standing.won))
+			})
+			.collect();
+		let mut ranking: Vec<_> = keys.into_iter().zip(standings).collect();
+		// the sort is stable, so players level on every count stay in order of registration.
+		ranking.sort_by_key(|(key, _)| *key);
+		ranking.into_iter().map(|(_, (player, _))| player).collect()
+	}
+
+	/// Closes the seasons of the leagues that end at block `now`. Games still being played
+	/// no longer count.
+	fn close_leagues(now: BlockNumber) -> Weight {
+		let ending = <LeagueEnds<T>>::take(now);
+		let mut weight = T::DbWeight::get().reads_writes(1, 1);
+		for league_id in ending {
+			if let Some(mut league) = Self::get_league(&league_id) {
+				// the ranking has every player once.
+				let ranking = Self::league_ranking(league_id, &league);
+				league.ranking = BoundedVec::truncate_from(ranking);
+				league.status = TournamentStatus::Finished;
+				Self::deposit_event(Event::LeagueClosed(league_id, league.ranking.to_vec()));
+				weight = weight
+					.saturating_add(T::DbWeight::get().reads(league.players.len() as u64));
+				<Leagues<T>>::insert(&league_id, league);
+			}
+			weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+		}
+		weight
+	}
+
+	/// Creates the games of a tournament round. `slots` has the players in bracket order,
+	/// and neighbours are paired: a player whose neighbour is `None` gets a bye.
+	fn pair_tournament_round(
+		tournament_id: H256,
+		tournament: &mut Tournament<T>,
+		slots: Vec<Option<T::AccountId>>,
+	) -> Result<(), Error<T>> {
+		tournament.matches = BoundedVec::default();
+		for pair in slots.chunks(2) {
+			let mut players = pair.iter().flatten().cloned();
+			let player_1 = match players.next() {
+				Some(player) => player,
+				None => continue,
+			};
+			let player_2 = players.next();
+			let (board_id, winner) = match &player_2 {
+				Some(player_2) => {
+					let board_id =
+						Self::create_game(player_1.clone(), player_2.clone(), tournament.settings)?;
+					<TournamentBoards<T>>::insert(&board_id, tournament_id);
+					(Some(board_id), None)
+				},
+				None => (None, Some(player_1.clone())),
+			};
+			let pairing = TournamentMatch { player_1, player_2, board_id, winner };
+			tournament.matches.try_push(pairing).map_err(|_| <Error<T>>::TournamentFull)?;
+		}
+		Self::deposit_event(Event::TournamentRoundStarted(tournament_id, tournament.round));
+		Ok(())
+	}
+
+	/// Records the result of a tournament game that has ended. The winner goes through, and a
+	/// drawn game is replayed with the other player moving first. Once every match of the
+	/// round has a winner, the winner of the final wins the tournament, and otherwise anyone
+	/// can start the next round with `start_tournament_round`.
+	fn advance_tournament(board_id: H256, board: &ConnectFourBoardStruct<T::AccountId>) {
+		let tournament_id = match <TournamentBoards<T>>::take(&board_id) {
+			Some(tournament_id) => tournament_id,
+			None => return,
+		};
+		let mut tournament = match Self::get_tournament(&tournament_id) {
+			Some(tournament) => tournament,
+			None => return,
+		};
+		let index = match tournament.matches.iter().position(|m| m.board_id == Some(board_id)) {
+			Some(index) => index,
+			None => return,
+		};
+		match board.win_state {
+			WinState::Player(Player::One) =>
+				tournament.matches[index].winner = Some(board.player_1.clone()),
+			WinState::Player(Player::Two) =>
+				tournament.matches[index].winner = Some(board.player_2.clone()),
+			_ => {
+				let replay = Self::create_game(
+					board.player_2.clone(),
+					board.player_1.clone(),
+					tournament.settings,
+				);
+				match replay {
+					Ok(replay_id) => {
+						<TournamentBoards<T>>::insert(&replay_id, tournament_id);
+						tournament.matches[index].board_id = Some(replay_id);
+					},
+					Err(error) => log::error!("Could not replay a drawn game: {:?}", error),
+				}
+			},
+		}
+		if tournament.matches.iter().all(|m| m.winner.is_some()) {
+			let winners: Vec<_> =
+				tournament.matches.iter().filter_map(|m| m.winner.clone()).collect();
+			if let [winner] = &winners[..] {
+				tournament.status = TournamentStatus::Finished;
+				Self::deposit_event(Event::TournamentWon(winner.clone(), tournament_id));
+			} else {
+				Self::deposit_event(Event::TournamentRoundFinished(
+					tournament_id,
+					tournament.round,
+				));
+			}
+		}
+		<Tournaments<T>>::insert(&tournament_id, tournament);
+	}
+}
+
+/// The order in which seeds are placed in a single-elimination bracket of `size` slots, a
+/// power of two. Neighbours are paired, so seed 0 plays seed `size - 1`, and the top seeds
+/// can only meet in the last rounds.
+pub fn bracket_order(size: usize) -> Vec<usize> {
+	let mut order = sp_std::vec![0];
+	while order.len() < size {
+		let len = order.len() * 2;
+		order = order.into_iter().flat_map(|seed| [seed, len - 1 - seed]).collect();
+	}
+	order
+}
+
+/// The number of rounds of a league between `players` players: every player meets every
+/// other player once in each half of the season. With an odd number of players, one of them
+/// sits out every round.
+pub fn league_rounds(players: usize) -> u32 {
+	let slots = players + players % 2;
+	2 * slots.saturating_sub(1) as u32
+}
+
+/// The games of round `round`, starting at 1, of a league between `players` players, as
+/// pairs of the indices of the home and away players. The rounds of the first half of the
+/// season follow the circle method: the first player stays put and the others rotate. The
+/// second half repeats them with the sides swapped.
+pub fn league_round(players: usize, round: u32) -> Vec<(usize, usize)> {
+	// with an odd number of players, whoever is paired with the extra slot sits out.
+	let slots = players + players % 2;
+	let half = slots.saturating_sub(1);
+	if half == 0 || round == 0 {
+		return Vec::new()
+	}
+	let round = round as usize - 1;
+	let (turn, second_half) = (round % half, round >= half);
+	let mut circle = sp_std::vec![0];
+	circle.extend((0..half).map(|slot| 1 + (slot + turn) % half));
+	(0..slots / 2)
+		.map(|slot| {
+			let (home, away) = (circle[slot], circle[slots - 1 - slot]);
+			// the first player alternates between home and away.
+			if slot == 0 && turn % 2 == 1 {
+				(away, home)
+			} else {
+				(home, away)
+			}
+		})
+		.filter(|&(home, away)| home < players && away < players)
+		.map(|(home, away)| if second_half { (away, home) } else { (home, away) })
+		.collect()
+}
+
+/// The most steps the search for the pairings of a Swiss round takes in all, which bounds
+/// the work of pairing a round.
+const MAX_PAIRING_STEPS: u32 = 10_000;
+
+/// Pairs the players of `order`, best placed first, with as few rematches as possible.
+/// Going down the players, each player is paired with the next player they haven't met, as
+/// long as the players below them can still be paired, and a rematch is only made when no
+/// pairing of the round has fewer. `met` tells whether two players have met. If the search
+/// takes more than `MAX_PAIRING_STEPS` steps, each player is paired with the next player
+/// they haven't met instead, without looking ahead.
+pub fn pair_swiss_players(
+	order: &[usize],
+	met: impl Fn(usize, usize) -> bool,
+) -> Vec<(usize, usize)> {
+	let mut paired = sp_std::vec![false; order.len()];
+	let mut pairs = Vec::with_capacity(order.len() / 2);
+	let mut steps = 0;
+	for rematches in 0..=order.len() / 2 {
+		if pair_with_rematches(order, &met, rematches, &mut paired, &mut pairs, &mut steps) {
+			return pairs
+		}
+		if steps > MAX_PAIRING_STEPS {
+			break
+		}
+	}
+	pair_greedily(order, &met)
+}
+
+/// Pairs the unpaired players of `order` with at most `rematches` rematches, adding the
+/// pairs to `pairs`, see `pair_swiss_players`. Returns whether it could, leaving `paired`
+/// and `pairs` as they were if not.
+fn pair_with_rematches(
+	order: &[usize],
+	met: &impl Fn(usize, usize) -> bool,
+	rematches: usize,
+	paired: &mut [bool],
+	pairs: &mut Vec<(usize, usize)>,
+	steps: &mut u32,
+) -> bool {
+	let first = match paired.iter().position(|&is_paired| !is_paired) {
+		Some(first) => first,
+		None => return true,
+	};
+	*steps += 1;
+	if *steps > MAX_PAIRING_STEPS {
+		return false
+	}
+	let player = order[first];
+	paired[first] = true;
+	// the players they haven't met come first, and then the others, each in order.
+	for rematch in 0..=rematches.min(1) {
+		for next in first + 1..order.len() {
+			if paired[next] || met(player, order[next]) != (rematch == 1) {
+				continue
+			}
+			paired[next] = true;
+			pairs.push((player, order[next]));
+			if pair_with_rematches(order, met, rematches - rematch, paired, pairs, steps) {
+				return true
+			}
+			pairs.pop();
+			paired[next] = false;
+		}
+	}
+	paired[first] = false;
+	false
+}
+
+/// Pairs each player of `order` with the next unpaired player they haven't met, or the next
+/// unpaired player if they have met all of them.
+fn pair_greedily(order: &[usize], met: &impl Fn(usize, usize) -> bool) -> Vec<(usize, usize)> {
+	let mut rest = order.to_vec();
+	let mut pairs = Vec::with_capacity(order.len() / 2);
+	while rest.len() >= 2 {
+		let player = rest.remove(0);
+		let next = rest.iter().position(|&other| !met(player, other)).unwrap_or(0);
+		pairs.push((player, rest.remove(next)));
+	}
+	pairs
+}
+
+/// The most blocks `enqueue` looks through for a queue with room, starting from the block
+/// an entry is due, which bounds the work of queueing it.
+const MAX_QUEUE_SEARCH: u32 = 16;
+
+/// Points scored in a Swiss tournament for a win or a bye, and for a draw.
+const SWISS_WIN: u32 = 2;
+const SWISS_DRAW: u32 = 1;
+
+/// Scores and expected scores of the Elo rating system are fixed-point numbers, in parts per
+/// million: a win scores `SCORE_SCALE`, a draw half of it, and a loss nothing.
+pub const SCORE_SCALE: u32 = 1_000_000;
+
+/// Rating difference between two consecutive samples of `EXPECTED_SCORES`.
+const EXPECTED_SCORE_STEP: u32 = 25;
+
+/// Expected score of the weaker player, `1 / (1 + 10^(difference / 400))`, for rating
+/// differences of 0, 25, ..., 800 points.
+const EXPECTED_SCORES: [u32; 33] = [
+	500000, 464084, 428537, 393712, 359935, 327490, 296615, 267493, 240253, 214973, 191682,
+	170367, 150980, 133443, 117662, 103523, 90909, 79695, 69758, 60978, 53240, 46435, 40463,
+	35231, 30653, 26654, 23164, 20122, 17472, 15166, 13160, 11416, 9901,
+];
+
+/// Expected score of a player rated `rating` against an `opponent`, in parts per million.
+/// Rating differences beyond 800 points are capped.
+pub fn expected_score(rating: u32, opponent: u32) -> u32 {
+	let max_difference = EXPECTED_SCORE_STEP * (EXPECTED_SCORES.len() as u32 - 1);
+	let difference = (rating.max(opponent) - rating.min(opponent)).min(max_difference);
+	let index = (difference / EXPECTED_SCORE_STEP) as usize;
+	let remainder = difference % EXPECTED_SCORE_STEP;
+	// interpolate linearly between the two nearest samples.
+	let lower = EXPECTED_SCORES[index];
+	let weaker_score = if remainder == 0 {
+		lower
+	} else {
+		let upper = EXPECTED_SCORES[index + 1];
+		lower - (lower - upper) * remainder / EXPECTED_SCORE_STEP
+	};
+	if rating >= opponent {
+		SCORE_SCALE - weaker_score
+	} else {
+		weaker_score
+	}
+}
+
+/// The Elo rating of a player rated `rating` after scoring `score` (in parts per million)
+/// against an `opponent`, rounded to the nearest point.
+pub fn new_rating(rating: u32, opponent: u32, score: u32, k_factor: u32) -> u32 {
+	let scale = SCORE_SCALE as i64;
+	let change = k_factor as i64 * (score as i64 - expected_score(rating, opponent) as i64);
+	let change = if change >= 0 { (change + scale / 2) / scale } else { (change - scale / 2) / scale };
+	(rating as i64 + change).max(0).min(u32::MAX as i64) as u32
 }
@@ -0,0 +1,182 @@
+//! Storage migrations for the connect four pallet.
+use super::*;
+use codec::Decode;
+use frame_support::{
+	pallet_prelude::ValueQuery,
+	storage_alias,
+	traits::{GetStorageVersion, StorageVersion},
+	Blake2_128Concat,
+};
+
+/// Version 1 brings the boards of the first release to the current layout. There is no
+/// limit on the number of those boards, so they are migrated a few at a time in the blocks
+/// after the upgrade, and the calls of the pallet fail until all of them are.
+pub mod v1 {
+	use super::*;
+
+	/// Storage as it was before version 1.
+	mod old {
+		use super::*;
+
+		/// A board as first stored, with its position as a grid of coins.
+		#[derive(Decode)]
+		pub(crate) struct ConnectFourBoardStruct<AccountId> {
+			pub(crate) state: Option<Vec<Vec<Coin>>>,
+			pub(crate) player_1: AccountId,
+			pub(crate) player_2: AccountId,
+			pub(crate) challenge_accepted: bool,
+			pub(crate) id: H256,
+			pub(crate) active: bool,
+			pub(crate) last_played: Option<AccountId>,
+			pub(crate) win_state: WinState,
+		}
+
+		#[storage_alias]
+		pub(crate) type ConnectFourBoards<T: Config> =
+			StorageValue<Pallet<T>, Vec<H256>, ValueQuery>;
+
+		#[storage_alias]
+		pub(crate) type ConnectFourBoardById<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			H256,
+			ConnectFourBoardStruct<<T as frame_system::Config>::AccountId>,
+		>;
+
+		#[storage_alias]
+		pub(crate) type Challenges<T: Config> = StorageDoubleMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			<T as frame_system::Config>::AccountId,
+			Blake2_128Concat,
+			<T as frame_system::Config>::AccountId,
+			H256,
+		>;
+	}
+
+	/// The boards stored before version 1 that haven't been migrated yet, last one first.
+	#[storage_alias]
+	pub(crate) type UnmigratedBoards<T: Config> = StorageValue<Pallet<T>, Vec<H256>>;
+
+	/// The weight of migrating one board.
+	fn board_weight<T: Config>() -> Weight {
+		T::DbWeight::get().reads_writes(5, 8).saturating_add(Pallet::<T>::enqueue_weight())
+	}
+
+	/// Whether boards stored before version 1 are still being migrated.
+	pub fn migrating<T: Config>() -> bool {
+		<UnmigratedBoards<T>>::exists()
+	}
+
+	/// Sets the list of all boards aside to be migrated by `migrate_boards`, and starts
+	/// pruning and checking move deadlines from the current block. Nothing else was stored
+	/// before version 1.
+	pub fn migrate<T: Config>() -> Weight {
+		let on_chain_version = Pallet::<T>::on_chain_storage_version();
+		if on_chain_version >= 1 {
+			log::info!("Skipping connect four v1 migration, storage is at {:?}", on_chain_version);
+			return T::DbWeight::get().reads(1)
+		}
+		let mut board_ids = <old::ConnectFourBoards<T>>::take();
+		let boards = board_ids.len();
+		if !board_ids.is_empty() {
+			board_ids.reverse();
+			<UnmigratedBoards<T>>::put(board_ids);
+		}
+		let now = Pallet::<T>::current_block();
+		<NextPruneBlock<T>>::put(now);
+		<NextDeadlineBlock<T>>::put(now);
+		StorageVersion::new(1).put::<Pallet<T>>();
+		log::info!("Migrating {} connect four boards to v1", boards);
+		T::DbWeight::get().reads_writes(2, 5)
+	}
+
+	/// Migrates the boards set aside by `migrate` for as long as the weight of the next board
+	/// fits in `limit`. The rest are migrated in a later block.
+	pub fn migrate_boards<T: Config>(limit: Weight) -> Weight {
+		let db_weight = T::DbWeight::get();
+		let mut weight = db_weight.reads(1);
+		let mut board_ids = match <UnmigratedBoards<T>>::get() {
+			Some(board_ids) => board_ids,
+			None => return weight,
+		};
+		weight = weight.saturating_add(db_weight.writes(1));
+		while weight.saturating_add(board_weight::<T>()) <= limit {
+			let board_id = match board_ids.pop() {
+				Some(board_id) => board_id,
+				None => break,
+			};
+			weight = weight.saturating_add(board_weight::<T>());
+			migrate_board::<T>(board_id);
+		}
+		if board_ids.is_empty() {
+			<UnmigratedBoards<T>>::kill();
+			log::info!("Migrated all connect four boards to v1");
+		} else {
+			<UnmigratedBoards<T>>::put(board_ids);
+		}
+		weight
+	}
+
+	/// Gives a board the classic 6 x 7 settings and indexes it. Its last move is set to now,
+	/// so the player to move in an ongoing game has `MoveTimeout` blocks to move, and a
+	/// finished game is pruned `PruneAfter` blocks from now. The position of games that
+	/// ended wasn't kept, so it can't be recovered.
+	fn migrate_board<T: Config>(board_id: H256) {
+		let old = match <old::ConnectFourBoardById<T>>::take(&board_id) {
+			Some(old) => old,
+			None => {
+				log::warn!("Dropping connect four board {:?}, which can't be decoded", board_id);
+				return
+			},
+		};
+		let finished = old.challenge_accepted && !old.active;
+		// boards were removed from `Challenges` when their game ended.
+		if !finished {
+			for (player, other_player) in
+				[(&old.player_1, &old.player_2), (&old.player_2, &old.player_1)]
+			{
+				if let Some(id) = <old::Challenges<T>>::take(player, other_player) {
+					let boards = BoundedVec::truncate_from(sp_std::vec![id]);
+					<Challenges<T>>::insert(player, other_player, boards);
+				}
+			}
+		}
+		let now = Pallet::<T>::current_block();
+		let moves_played =
+			old.state.iter().flatten().flatten().filter(|&&coin| coin != Coin::Empty).count();
+		let board = ConnectFourBoardStruct {
+			state: old.state.map(|grid| Bitboards::from_grid(&grid)),
+			player_1: old.player_1,
+			player_2: old.player_2,
+			challenge_accepted: old.challenge_accepted,
+			id: old.id,
+			active: old.active,
+			last_played: old.last_played,
+			win_state: old.win_state,
+			last_move_at: now,
+			settings: GameSettings::default(),
+			winning_line: None,
+			draw_offer: None,
+			takeback_request: None,
+			clock: None,
+			first_player: Player::One,
+			swapped: false,
+			moves_played: moves_played as u32,
+		};
+		let queued = if finished {
+			let prune_at = now.saturating_add(T::PruneAfter::get());
+			Pallet::<T>::enqueue::<PruneQueue<T>>(prune_at, board_id).map(|_| ())
+		} else if board.challenge_accepted {
+			Pallet::<T>::schedule_move_deadline(board_id, &board)
+		} else {
+			Ok(())
+		};
+		// the game goes on without a move deadline, or waits to be reaped by hand.
+		if let Err(error) = queued {
+			log::warn!("Could not queue connect four board {:?}: {:?}", board_id, error);
+		}
+		<ConnectFourBoardById<T>>::insert(&board_id, board);
+		Pallet::<T>::index_board(board_id);
+	}
+}
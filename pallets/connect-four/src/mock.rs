@@ -1,10 +1,11 @@
 //! Mock library for tests.
 use crate::{self as pallet_connect_four};
-use frame_support::traits::{ConstU16, ConstU64};
+use frame_support::traits::{ConstU16, ConstU64, Hooks};
 use frame_system as system;
 use sp_core::H256;
 use sp_runtime::{
 	testing::Header,
+	Perbill,
 	traits::{BlakeTwo256, IdentityLookup},
 };
 use frame_support::parameter_types;
@@ -22,6 +23,7 @@ frame_support::construct_runtime!(
 		System: frame_system,
 		ConnectFour: pallet_connect_four::{Pallet, Call, Storage, Event<T>},
 		CollectiveFlip: pallet_randomness_collective_flip::{Pallet, Storage},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
 
 	}
 );
@@ -29,7 +31,7 @@ frame_support::construct_runtime!(
 impl pallet_randomness_collective_flip::Config for Test {}
 
 impl system::Config for Test {
-	type AccountData = ();
+	type AccountData = pallet_balances::AccountData<u64>;
 	type AccountId = u64;
 	type BaseCallFilter = frame_support::traits::Everything;
 	type BlockHashCount = ConstU64<250>;
@@ -37,7 +39,7 @@ impl system::Config for Test {
 	type BlockNumber = u64;
 	type BlockWeights = ();
 	type Call = Call;
-	type DbWeight = ();
+	type DbWeight = frame_support::weights::constants::RocksDbWeight;
 	type Event = Event;
 	type Hash = H256;
 	type Hashing = BlakeTwo256;
@@ -55,10 +57,42 @@ impl system::Config for Test {
 	type Version = ();
 }
 
+impl pallet_balances::Config for Test {
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ConstU64<1>;
+	type AccountStore = System;
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type WeightInfo = ();
+}
+
 parameter_types! {
 	pub const PointsForWin: u32 = 5;
 	pub const PointsForLoss: u32 = 2;
 	pub const PointsForDraw: u32 = 3;
+	pub const MoveTimeout: u64 = 10;
+	pub const OpenChallengeExpiry: u64 = 20;
+	pub const MaxRows: u32 = 8;
+	pub const MaxColumns: u32 = 9;
+	pub const InitialRating: u32 = 1500;
+	pub const RatingKFactor: u32 = 32;
+	pub const MaxGamesPerPair: u32 = 2;
+	pub const MaxMoves: u32 = 100;
+	pub const TakebacksInRatedGames: bool = false;
+	pub const HouseFee: Perbill = Perbill::from_percent(10);
+	pub const HouseAccount: u64 = HOUSE;
+	pub const MaxTournamentPlayers: u32 = 8;
+	pub const MaxLeaguePlayers: u32 = 4;
+	pub const LeagueSeasonLength: u64 = 5;
+	pub const BettingCutoff: u32 = 4;
+	pub const MaxBetsPerGame: u32 = 4;
+	pub const MinimumBet: u64 = 10;
+	pub const PruneAfter: u64 = 50;
+	pub static GameDeposit: u64 = 0;
+	pub static MaxQueuedPerBlock: u32 = 16;
 }
 
 impl pallet_connect_four::Config for Test {
@@ -67,11 +101,56 @@ impl pallet_connect_four::Config for Test {
 	type PointsForWin = PointsForWin;
 	type PointsForLoss = PointsForLoss;
 	type PointsForDraw = PointsForDraw;
+	type MoveTimeout = MoveTimeout;
+	type OpenChallengeExpiry = OpenChallengeExpiry;
+	type MaxRows = MaxRows;
+	type MaxColumns = MaxColumns;
+	type InitialRating = InitialRating;
+	type RatingKFactor = RatingKFactor;
+	type MaxGamesPerPair = MaxGamesPerPair;
+	type MaxMoves = MaxMoves;
+	type TakebacksInRatedGames = TakebacksInRatedGames;
+	type Currency = Balances;
+	type HouseFee = HouseFee;
+	type HouseAccount = HouseAccount;
+	type MaxTournamentPlayers = MaxTournamentPlayers;
+	type MaxLeaguePlayers = MaxLeaguePlayers;
+	type LeagueSeasonLength = LeagueSeasonLength;
+	type BettingCutoff = BettingCutoff;
+	type MaxBetsPerGame = MaxBetsPerGame;
+	type MinimumBet = MinimumBet;
+	type PruneAfter = PruneAfter;
+	type GameDeposit = GameDeposit;
+	type MaxQueuedPerBlock = MaxQueuedPerBlock;
 }
 
+/// The account house fees are paid to.
+pub const HOUSE: u64 = 99;
+/// The balance every player account starts with.
+pub const INITIAL_BALANCE: u64 = 1_000;
+/// The balance the house account starts with, the existential deposit.
+pub const HOUSE_BALANCE: u64 = 1;
+
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	let mut ext: sp_io::TestExternalities = system::GenesisConfig::default().build_storage::<Test>().unwrap().into();
+	let mut storage = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: (1..=20)
+			.map(|account| (account, INITIAL_BALANCE))
+			.chain([(HOUSE, HOUSE_BALANCE)])
+			.collect(),
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+	let mut ext: sp_io::TestExternalities = storage.into();
     ext.execute_with(|| System::set_block_number(1));
 	ext
 }
+
+// Advance the chain to block `n`, running the pallet's hooks on the way.
+pub fn run_to_block(n: u64) {
+	while System::block_number() < n {
+		System::set_block_number(System::block_number() + 1);
+		ConnectFour::on_initialize(System::block_number());
+	}
+}
@@ -1,23 +1,31 @@
 //! tests for connect-four
 use crate::{mock::*, Error};
-use crate::{Challenges, Coin, WinState, Player, AccountScoreCard};
+use crate::{Challenges, ConnectFourBoardById, ConnectFourBoards, Lobby, Coin, WinState, Player, AccountScoreCard, AccountRating, GameSettings, GameVariant};
+use crate::{expected_score, new_rating, SCORE_SCALE, Bitboards, Direction, GameError, Move, MoveKind, WinningLine, MAX_WIN_LENGTH};
+use crate::{bracket_order, league_round, league_rounds, pair_swiss_players, Tournaments, TournamentStatus, Leagues, SwissTournaments, TimeControl, Clock, FirstMove, MoveDeadlines};
 use frame_support::{assert_noop, assert_ok};
+use sp_core::H256;
+
+/// The ID of the last board created between two accounts.
+fn board_between(player: u64, other_player: u64) -> H256 {
+	*ConnectFour::get_challenges(player, other_player).last().unwrap()
+}
 
 /// An account can challege another account.
 #[test]
 fn can_challenge_a_player() {
 	new_test_ext().execute_with(|| {
 		// challenge account 2 from account 1
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
 		// check if there are challenges between these two, and that the board ID exists.
-		let board_id_1 = ConnectFour::get_challenge(1, 2);
-		let board_id_2 = ConnectFour::get_challenge(2, 1);
-		assert_eq!(board_id_1, board_id_2);
+		let board_ids_1 = ConnectFour::get_challenges(1, 2);
+		let board_ids_2 = ConnectFour::get_challenges(2, 1);
+		assert_eq!(board_ids_1.len(), 1);
+		assert_eq!(board_ids_1, board_ids_2);
 		// there should only be on board
-		let boards = ConnectFour::get_all_board_ids();
-		assert_eq!(boards.len(), 1);
+		assert_eq!(<ConnectFourBoards<Test>>::count(), 1);
 		// this board should be queriable from the ID
-		let board = ConnectFour::get_connect_four_board_by_id(board_id_1).unwrap();
+		let board = ConnectFour::get_connect_four_board_by_id(board_ids_1[0]).unwrap();
 		assert_eq!(board.player_1, 1);
 		assert_eq!(board.player_2, 2);
 		assert_eq!(board.active, false);
@@ -25,29 +33,103 @@ fn can_challenge_a_player() {
 	})
 }
 
+/// Challenges made in the same block get different board IDs, derived from the players,
+/// the nonce and the block.
+#[test]
+fn challenges_in_the_same_block_get_different_ids() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_ids = ConnectFour::get_challenges(1, 2);
+		assert_ne!(board_ids[0], board_ids[1]);
+		let expected = H256::from(sp_io::hashing::blake2_256(&(1u64, Some(2u64), 0u64, 1u64).encode()));
+		assert_eq!(board_ids[0], expected);
+		assert_eq!(ConnectFour::get_connect_four_board_by_id(board_ids[0]).unwrap().player_1, 1);
+		assert_eq!(ConnectFour::get_connect_four_board_by_id(board_ids[1]).unwrap().player_1, 1);
+	})
+}
+
+/// A board is never created over an existing one.
+#[test]
+fn cannot_create_a_board_with_a_taken_id() {
+	new_test_ext().execute_with(|| {
+		let taken = H256::from(sp_io::hashing::blake2_256(&(1u64, Some(2u64), 0u64, 1u64).encode()));
+		<ConnectFourBoardById<Test>>::insert(taken, crate::ConnectFourBoardStruct::default());
+		assert_noop!(
+			ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None),
+			Error::<Test>::BoardIdTaken
+		);
+	})
+}
+
+/// Every board gets the next sequential game index.
+#[test]
+fn boards_are_indexed_in_order() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		assert_ok!(ConnectFour::create_open_challenge(Origin::signed(3), GameSettings::default()));
+		let open_challenge = <Lobby<Test>>::iter_keys().next().unwrap();
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 4, GameSettings::default(), None));
+		assert_ok!(ConnectFour::join_open_challenge(Origin::signed(5), open_challenge));
+		assert_eq!(ConnectFour::get_game_count(), 3);
+		assert_eq!(ConnectFour::get_board_by_index(0), Some(board_between(1, 2)));
+		assert_eq!(ConnectFour::get_board_by_index(1), Some(board_between(1, 4)));
+		assert_eq!(ConnectFour::get_board_by_index(2), Some(open_challenge));
+		assert_eq!(ConnectFour::get_board_by_index(3), None);
+	})
+}
+
 /// An account cannot challenge itself
 #[test]
 fn cannot_challenge_self() {
 	new_test_ext().execute_with(|| {
-		assert_noop!(ConnectFour::challenge_player(Origin::signed(1), 1), Error::<Test>::CannotPlayYourself);
+		assert_noop!(ConnectFour::challenge_player(Origin::signed(1), 1, GameSettings::default(), None), Error::<Test>::CannotPlayYourself);
+	})
+}
+
+/// An account can have several games with another account, up to a limit.
+#[test]
+fn can_challenge_a_player_several_times() {
+	new_test_ext().execute_with(|| {
+		for _ in 0..MaxGamesPerPair::get() {
+			assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		}
+		let board_ids = ConnectFour::get_challenges(1, 2);
+		assert_eq!(board_ids.len() as u32, MaxGamesPerPair::get());
+		assert_ne!(board_ids[0], board_ids[1]);
+		assert_noop!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None), Error::<Test>::ChallengeExists);
 	})
 }
 
-/// An account cannot challenge another when a challenge exists
+/// Challenges made by either account count towards the limit.
 #[test]
-fn cannot_challenge_a_player_twice() {
+fn cannot_challenge_a_challenger_beyond_limit() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_noop!(ConnectFour::challenge_player(Origin::signed(1), 2), Error::<Test>::ChallengeExists);
+		for _ in 0..MaxGamesPerPair::get() {
+			assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		}
+		assert_noop!(ConnectFour::challenge_player(Origin::signed(2), 1, GameSettings::default(), None), Error::<Test>::ChallengeExists);
 	})
 }
 
-/// An account cannot challenge someone who has an active challenge for them.
+/// Several games between the same two accounts are played independently.
 #[test]
-fn cannot_challenge_a_challenger() {
+fn can_play_concurrent_games() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_noop!(ConnectFour::challenge_player(Origin::signed(2), 1), Error::<Test>::ChallengeExists);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let first_board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(2), 1, GameSettings::default(), None));
+		let second_board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), first_board_id));
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(1), second_board_id));
+		// each game has its own turns, and account 2 moves first on the second board.
+		assert_ok!(ConnectFour::play(Origin::signed(1), first_board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), second_board_id, 0));
+		assert_noop!(ConnectFour::play(Origin::signed(1), first_board_id, 0), Error::<Test>::NotYourMove);
+		// ending one game leaves the other one listed.
+		assert_ok!(ConnectFour::resign(Origin::signed(2), first_board_id));
+		assert_eq!(ConnectFour::get_challenges(1, 2), vec![second_board_id]);
+		assert_eq!(ConnectFour::get_challenges(2, 1), vec![second_board_id]);
 	})
 }
 
@@ -55,8 +137,9 @@ fn cannot_challenge_a_challenger() {
 #[test]
 fn can_accept_a_challenge() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
 		let score_card_1 = <AccountScoreCard<Test>>::get(1);
 		assert_eq!(score_card_1.played, 1);
 		let score_card_2 = <AccountScoreCard<Test>>::get(2);
@@ -68,8 +151,9 @@ fn can_accept_a_challenge() {
 #[test]
 fn cannot_accept_own_challenge() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_noop!(ConnectFour::accept_challenge(Origin::signed(1), 2), Error::<Test>::CannotAcceptYourOwnChallenge);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_noop!(ConnectFour::accept_challenge(Origin::signed(1), board_id), Error::<Test>::CannotAcceptYourOwnChallenge);
 	})
 }
 
@@ -77,7 +161,17 @@ fn cannot_accept_own_challenge() {
 #[test]
 fn cannot_accept_non_existent_challenge() {
 	new_test_ext().execute_with(|| {
-		assert_noop!(ConnectFour::accept_challenge(Origin::signed(1), 2), Error::<Test>::ChallengeDoesNotExist);
+		assert_noop!(ConnectFour::accept_challenge(Origin::signed(1), H256::repeat_byte(1)), Error::<Test>::ChallengeDoesNotExist);
+	})
+}
+
+/// An account cannot accept a challenge made to someone else
+#[test]
+fn cannot_accept_others_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_noop!(ConnectFour::accept_challenge(Origin::signed(3), board_id), Error::<Test>::NotYourBoard);
 	})
 }
 
@@ -85,7 +179,118 @@ fn cannot_accept_non_existent_challenge() {
 #[test]
 fn cannot_accept_challenge_to_self() {
 	new_test_ext().execute_with(|| {
-		assert_noop!(ConnectFour::challenge_player(Origin::signed(1), 1), Error::<Test>::CannotPlayYourself);
+		assert_noop!(ConnectFour::challenge_player(Origin::signed(1), 1, GameSettings::default(), None), Error::<Test>::CannotPlayYourself);
+	})
+}
+
+/// An account can decline a challenge it received.
+#[test]
+fn can_decline_a_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::decline_challenge(Origin::signed(2), board_id));
+		// the challenge should be unmapped in both directions.
+		assert_eq!(<Challenges<Test>>::contains_key(1, 2), false);
+		assert_eq!(<Challenges<Test>>::contains_key(2, 1), false);
+		// the board should be purged.
+		assert!(ConnectFour::get_connect_four_board_by_id(board_id).is_none());
+		assert_eq!(<ConnectFourBoards<Test>>::count(), 0);
+		// the pair can play again.
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(2), 1, GameSettings::default(), None));
+	})
+}
+
+/// An account cannot decline a challenge it made.
+#[test]
+fn cannot_decline_own_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_noop!(ConnectFour::decline_challenge(Origin::signed(1), board_id), Error::<Test>::CannotDeclineYourOwnChallenge);
+	})
+}
+
+/// An account cannot decline a challenge that doesn't exist
+#[test]
+fn cannot_decline_non_existent_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(ConnectFour::decline_challenge(Origin::signed(2), H256::repeat_byte(1)), Error::<Test>::ChallengeDoesNotExist);
+	})
+}
+
+/// An account cannot decline a challenge made to someone else
+#[test]
+fn cannot_decline_others_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_noop!(ConnectFour::decline_challenge(Origin::signed(3), board_id), Error::<Test>::NotYourBoard);
+	})
+}
+
+/// An account cannot decline a challenge that has already been accepted.
+#[test]
+fn cannot_decline_accepted_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::decline_challenge(Origin::signed(2), board_id), Error::<Test>::ActiveGameExists);
+	})
+}
+
+/// An account can withdraw a challenge it made.
+#[test]
+fn can_withdraw_a_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::withdraw_challenge(Origin::signed(1), board_id));
+		assert_eq!(<Challenges<Test>>::contains_key(1, 2), false);
+		assert_eq!(<Challenges<Test>>::contains_key(2, 1), false);
+		assert!(ConnectFour::get_connect_four_board_by_id(board_id).is_none());
+		assert_eq!(<ConnectFourBoards<Test>>::count(), 0);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+	})
+}
+
+/// An account cannot withdraw a challenge it received.
+#[test]
+fn cannot_withdraw_received_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_noop!(ConnectFour::withdraw_challenge(Origin::signed(2), board_id), Error::<Test>::CannotWithdrawReceivedChallenge);
+	})
+}
+
+/// An account cannot withdraw a challenge that doesn't exist
+#[test]
+fn cannot_withdraw_non_existent_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(ConnectFour::withdraw_challenge(Origin::signed(1), H256::repeat_byte(1)), Error::<Test>::ChallengeDoesNotExist);
+	})
+}
+
+/// An account cannot withdraw someone else's challenge
+#[test]
+fn cannot_withdraw_others_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_noop!(ConnectFour::withdraw_challenge(Origin::signed(3), board_id), Error::<Test>::NotYourBoard);
+	})
+}
+
+/// An account cannot withdraw a challenge that has already been accepted.
+#[test]
+fn cannot_withdraw_accepted_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::withdraw_challenge(Origin::signed(1), board_id), Error::<Test>::ActiveGameExists);
 	})
 }
 
@@ -93,9 +298,118 @@ fn cannot_accept_challenge_to_self() {
 #[test]
 fn cannot_accept_challenge_twice() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
-		assert_noop!(ConnectFour::accept_challenge(Origin::signed(2), 1), Error::<Test>::ActiveGameExists);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::accept_challenge(Origin::signed(2), board_id), Error::<Test>::ActiveGameExists);
+	})
+}
+
+/// An account can list an open challenge in the lobby.
+#[test]
+fn can_create_open_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_open_challenge(Origin::signed(1), GameSettings::default()));
+		let board_id = <Lobby<Test>>::iter_keys().next().unwrap();
+		let challenge = ConnectFour::get_open_challenge(board_id).unwrap();
+		assert_eq!(challenge.creator, 1);
+		assert_eq!(challenge.expires_at, 1 + OpenChallengeExpiry::get());
+		// the board is only created once someone joins.
+		assert!(ConnectFour::get_connect_four_board_by_id(board_id).is_none());
+	})
+}
+
+/// An account cannot list an open challenge with invalid settings.
+#[test]
+fn cannot_create_open_challenge_with_invalid_settings() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { win_length: 2, ..Default::default() };
+		assert_noop!(ConnectFour::create_open_challenge(Origin::signed(1), settings), Error::<Test>::InvalidGameSettings);
+	})
+}
+
+/// Any account can join an open challenge, which starts the game.
+#[test]
+fn can_join_open_challenge() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { rows: 7, columns: 8, win_length: 5, ..Default::default() };
+		assert_ok!(ConnectFour::create_open_challenge(Origin::signed(1), settings));
+		let board_id = <Lobby<Test>>::iter_keys().next().unwrap();
+		assert_ok!(ConnectFour::join_open_challenge(Origin::signed(3), board_id));
+		// the challenge has left the lobby.
+		assert!(ConnectFour::get_open_challenge(board_id).is_none());
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.player_1, 1);
+		assert_eq!(board.player_2, 3);
+		assert_eq!(board.settings, settings);
+		assert_eq!(board.challenge_accepted, true);
+		assert_eq!(board.active, true);
+		assert_eq!(ConnectFour::get_challenges(1, 3), vec![board_id]);
+		assert_eq!(ConnectFour::board_ids(0, 10), vec![board_id]);
+		assert_eq!(<AccountScoreCard<Test>>::get(1).played, 1);
+		assert_eq!(<AccountScoreCard<Test>>::get(3).played, 1);
+		// the creator moves first.
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+	})
+}
+
+/// An account cannot join its own open challenge.
+#[test]
+fn cannot_join_own_open_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_open_challenge(Origin::signed(1), GameSettings::default()));
+		let board_id = <Lobby<Test>>::iter_keys().next().unwrap();
+		assert_noop!(ConnectFour::join_open_challenge(Origin::signed(1), board_id), Error::<Test>::CannotPlayYourself);
+	})
+}
+
+/// An open challenge can only be joined once.
+#[test]
+fn cannot_join_open_challenge_twice() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_open_challenge(Origin::signed(1), GameSettings::default()));
+		let board_id = <Lobby<Test>>::iter_keys().next().unwrap();
+		assert_ok!(ConnectFour::join_open_challenge(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::join_open_challenge(Origin::signed(3), board_id), Error::<Test>::OpenChallengeDoesNotExist);
+	})
+}
+
+/// An account cannot join an open challenge from someone it already has the maximum number of challenges with.
+#[test]
+fn cannot_join_open_challenge_with_existing_challenge() {
+	new_test_ext().execute_with(|| {
+		for _ in 0..MaxGamesPerPair::get() {
+			assert_ok!(ConnectFour::challenge_player(Origin::signed(2), 1, GameSettings::default(), None));
+		}
+		assert_ok!(ConnectFour::create_open_challenge(Origin::signed(1), GameSettings::default()));
+		let board_id = <Lobby<Test>>::iter_keys().next().unwrap();
+		assert_noop!(ConnectFour::join_open_challenge(Origin::signed(2), board_id), Error::<Test>::ChallengeExists);
+	})
+}
+
+/// The creator can cancel an open challenge, but no one else can.
+#[test]
+fn can_cancel_open_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_open_challenge(Origin::signed(1), GameSettings::default()));
+		let board_id = <Lobby<Test>>::iter_keys().next().unwrap();
+		assert_noop!(ConnectFour::cancel_open_challenge(Origin::signed(2), board_id), Error::<Test>::NotYourChallenge);
+		assert_ok!(ConnectFour::cancel_open_challenge(Origin::signed(1), board_id));
+		assert_noop!(ConnectFour::join_open_challenge(Origin::signed(2), board_id), Error::<Test>::OpenChallengeDoesNotExist);
+	})
+}
+
+/// An open challenge that no one joins expires.
+#[test]
+fn open_challenge_expires() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_open_challenge(Origin::signed(1), GameSettings::default()));
+		let board_id = <Lobby<Test>>::iter_keys().next().unwrap();
+		run_to_block(OpenChallengeExpiry::get());
+		assert!(ConnectFour::get_open_challenge(board_id).is_some());
+		run_to_block(1 + OpenChallengeExpiry::get());
+		assert!(ConnectFour::get_open_challenge(board_id).is_none());
+		assert_noop!(ConnectFour::join_open_challenge(Origin::signed(2), board_id), Error::<Test>::OpenChallengeDoesNotExist);
 	})
 }
 
@@ -103,9 +417,10 @@ fn cannot_accept_challenge_twice() {
 #[test]
 fn cannot_make_first_move_if_not_challenger() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
-		assert_noop!(ConnectFour::play(Origin::signed(2), 1, 0), Error::<Test>::NotYourMove);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::play(Origin::signed(2), board_id, 0), Error::<Test>::NotYourMove);
 	})
 }
 
@@ -113,9 +428,133 @@ fn cannot_make_first_move_if_not_challenger() {
 #[test]
 fn can_make_first_move_if_challenger() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+	})
+}
+
+/// The challenger can let their opponent move first.
+#[test]
+fn opponent_moves_first_if_challenger_chooses() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { first_move: FirstMove::Opponent, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.first_player, Player::Two);
+		assert_eq!(board.player_to_move(), Player::Two);
+		assert_noop!(ConnectFour::play(Origin::signed(1), board_id, 0), Error::<Test>::NotYourMove);
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 1));
+		let moves = ConnectFour::get_move_history(board_id);
+		assert_eq!(moves[0].player, Player::Two);
+		assert_eq!(moves[1].player, Player::One);
+	})
+}
+
+/// The first move can go to either player, drawn when the challenge is accepted.
+#[test]
+fn random_first_move_is_drawn_when_accepted() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { first_move: FirstMove::Random, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_eq!(ConnectFour::get_connect_four_board_by_id(board_id).unwrap().first_player, Player::One);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		let (first, second) = match board.first_player {
+			Player::One => (1, 2),
+			Player::Two => (2, 1),
+		};
+		assert_noop!(ConnectFour::play(Origin::signed(second), board_id, 0), Error::<Test>::NotYourMove);
+		assert_ok!(ConnectFour::play(Origin::signed(first), board_id, 0));
+	})
+}
+
+/// Taking back the first move of a game the opponent started gives them the move again.
+#[test]
+fn can_take_back_first_move_of_opponent() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { first_move: FirstMove::Opponent, rated: false, ..Default::default() };
+		assert_ok!(ConnectFour::create_open_challenge(Origin::signed(1), settings));
+		let board_id = <Lobby<Test>>::iter_keys().next().unwrap();
+		assert_ok!(ConnectFour::join_open_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 3));
+		assert_ok!(ConnectFour::request_takeback(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::accept_takeback(Origin::signed(1), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.player_to_move(), Player::Two);
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 4));
+	})
+}
+
+/// Starts a game between accounts 1 and 2 played with the swap rule, returning its board.
+fn start_swap_rule_game() -> H256 {
+	let settings = GameSettings { swap_rule: true, ..Default::default() };
+	assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+	let board_id = board_between(1, 2);
+	assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+	board_id
+}
+
+/// Under the swap rule, the second player can take the opening coin as their own.
+#[test]
+fn swap_sides_takes_the_opening_coin() {
+	new_test_ext().execute_with(|| {
+		let board_id = start_swap_rule_game();
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 3));
+		assert_ok!(ConnectFour::swap_sides(Origin::signed(2), board_id));
+		System::assert_last_event(crate::Event::<Test>::SidesSwapped(2, board_id).into());
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!((board.player_1, board.player_2), (2, 1));
+		assert_eq!(board.swapped, true);
+		assert_eq!(board.player_to_move(), Player::Two);
+		assert_noop!(ConnectFour::play(Origin::signed(2), board_id, 3), Error::<Test>::NotYourMove);
+		// account 2 goes on to win with the opening coin.
+		for (player, column) in [(1, 0), (2, 3), (1, 0), (2, 3), (1, 1), (2, 3)] {
+			assert_ok!(ConnectFour::play(Origin::signed(player), board_id, column));
+		}
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.win_state, WinState::Player(Player::One));
+		assert_eq!(<AccountScoreCard<Test>>::get(2).won, 1);
+		assert_eq!(<AccountScoreCard<Test>>::get(1).lost, 1);
+	})
+}
+
+/// Sides can only be swapped once, by the second player, instead of their first move.
+#[test]
+fn cannot_swap_sides_out_of_turn() {
+	new_test_ext().execute_with(|| {
+		let board_id = start_game_between_1_and_2();
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 3));
+		assert_noop!(ConnectFour::swap_sides(Origin::signed(2), board_id), Error::<Test>::SwapRuleNotInPlay);
+		let board_id = start_swap_rule_game();
+		assert_noop!(ConnectFour::swap_sides(Origin::signed(2), board_id), Error::<Test>::CannotSwapSides);
+		assert_noop!(ConnectFour::swap_sides(Origin::signed(3), board_id), Error::<Test>::NotYourBoard);
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 3));
+		assert_noop!(ConnectFour::swap_sides(Origin::signed(1), board_id), Error::<Test>::CannotSwapSides);
+		assert_ok!(ConnectFour::swap_sides(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::swap_sides(Origin::signed(1), board_id), Error::<Test>::CannotSwapSides);
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 4));
+		assert_noop!(ConnectFour::swap_sides(Origin::signed(2), board_id), Error::<Test>::CannotSwapSides);
+	})
+}
+
+/// The bets on a player follow them when they swap sides.
+#[test]
+fn bets_follow_the_players_who_swap_sides() {
+	new_test_ext().execute_with(|| {
+		let board_id = start_swap_rule_game();
+		assert_ok!(ConnectFour::place_bet(Origin::signed(3), board_id, WinState::Player(Player::One), 100));
+		assert_ok!(ConnectFour::place_bet(Origin::signed(4), board_id, WinState::Draw, 100));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 3));
+		assert_ok!(ConnectFour::swap_sides(Origin::signed(2), board_id));
+		let bets = ConnectFour::get_bets(board_id);
+		assert_eq!(bets[0].outcome, WinState::Player(Player::Two));
+		assert_eq!(bets[1].outcome, WinState::Draw);
 	})
 }
 
@@ -123,10 +562,11 @@ fn can_make_first_move_if_challenger() {
 #[test]
 fn cannot_make_consecutive_moves() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_noop!(ConnectFour::play(Origin::signed(1), 2, 0), Error::<Test>::NotYourMove);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_noop!(ConnectFour::play(Origin::signed(1), board_id, 0), Error::<Test>::NotYourMove);
 	})
 }
 
@@ -134,10 +574,11 @@ fn cannot_make_consecutive_moves() {
 #[test]
 fn can_move_after_other_player_moves() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(2), 1, 0));
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 0));
 	})
 }
 
@@ -145,15 +586,16 @@ fn can_move_after_other_player_moves() {
 #[test]
 fn cannot_place_coin_on_full_column() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(2), 1, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(2), 1, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(2), 1, 0));
-		assert_noop!(ConnectFour::play(Origin::signed(1), 2, 0), Error::<Test>::ColumnFull);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 0));
+		assert_noop!(ConnectFour::play(Origin::signed(1), board_id, 0), Error::<Test>::ColumnFull);
 	})
 }
 
@@ -161,21 +603,29 @@ fn cannot_place_coin_on_full_column() {
 #[test]
 fn can_win_simple_game() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
 		// get board_id before starting
-		let board_id = ConnectFour::get_challenge(1, 2);
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(2), 1, 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(2), 1, 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(2), 1, 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
 		let  board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
 		assert_eq!(board.active, false, "The game should no longer be active");
 		assert_eq!(board.win_state, WinState::Player(Player::One), "Player 1 should be the winner.");
-		assert_eq!(board.state, None, "The game state should be purged and not kept on chain.");
+		// the final position is kept, with the line that won the game.
+		let state = board.grid().unwrap();
+		for row in 0..4 {
+			assert_eq!(state[row][0], Coin::Player1);
+			assert_eq!(state[row][1], if row < 3 { Coin::Player2 } else { Coin::Empty });
+		}
+		let winning_line = WinningLine { direction: Direction::Vertical, cells: vec![(0, 0), (1, 0), (2, 0), (3, 0)].try_into().unwrap() };
+		assert_eq!(board.winning_line, Some(winning_line.clone()));
+		System::assert_has_event(crate::Event::<Test>::GameWon(1, board_id, Some(winning_line)).into());
 		// ensure that the board is nolonger mapped as an active challenge
 		let board_exists = <Challenges<Test>>::contains_key(1, 2);
 		assert_eq!(board_exists, false, "The challenge should be unmapped between the two players.");
@@ -199,127 +649,677 @@ fn can_win_simple_game() {
 #[test]
 fn cannot_play_ended_game() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(2), 1, 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(2), 1, 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_ok!(ConnectFour::play(Origin::signed(2), 1, 1));
-		assert_ok!(ConnectFour::play(Origin::signed(1), 2, 0));
-		assert_noop!(ConnectFour::play(Origin::signed(2), 1,0), Error::<Test>::GameDoesNotExist);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_noop!(ConnectFour::play(Origin::signed(2), board_id, 0), Error::<Test>::GameEnded);
 	})
 }
 
-/// checks draw status and points
+/// A player can resign, conceding the game and its points to the opponent.
 #[test]
-fn can_draw_a_game() {
+fn can_resign_a_game() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
-		// get board_id before starting
-		let board_id = ConnectFour::get_challenge(1, 2);
-		let mut board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
-		// set the board state to a definite draw.
-		let draw_state = vec![
-			vec![Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1],
-			vec![Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player2, Coin::Player2, Coin::Player2, Coin::Player1],
-			vec![Coin::Player1, Coin::Player2, Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player2, Coin::Player2],
-			vec![Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player1],
-			vec![Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player1],
-			vec![Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1],
-		];
-		board.set_state(draw_state);
-		board.active = false;
-		let winstate = board.get_winner();
-		assert_eq!(winstate, WinState::Draw);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.active, false, "The game should no longer be active");
+		assert_eq!(board.win_state, WinState::Player(Player::One), "Player 1 should be the winner.");
+		assert_eq!(<Challenges<Test>>::contains_key(1, 2), false);
+		assert_eq!(<Challenges<Test>>::contains_key(2, 1), false);
+		let score_card_1 = <AccountScoreCard<Test>>::get(1);
+		assert_eq!(score_card_1.won, 1);
+		assert_eq!(score_card_1.points, PointsForWin::get() as i64);
+		let score_card_2 = <AccountScoreCard<Test>>::get(2);
+		assert_eq!(score_card_2.lost, 1);
+		assert_eq!(score_card_2.points, -1 * PointsForLoss::get() as i64);
+		// the pair can rematch.
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(2), 1, GameSettings::default(), None));
 	})
 }
-/// check several possible win-states
+
+/// The challenger can resign before making a move.
 #[test]
-fn check_win_states() {
+fn can_resign_before_first_move() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2));
-		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), 1));
-		// get board_id before starting
-		let board_id = ConnectFour::get_challenge(1, 2);
-		let mut board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
-		let winning_states = [
-			vec![
-				vec![Coin::Player1, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-			],
-			vec![
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Player2, Coin::Player2, Coin::Player2, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-			],
-			vec![
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Player1, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-			],
-			vec![
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Player2, Coin::Player2, Coin::Player2],
-			],
-			vec![
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-			],
-			vec![
-				vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-			],
-			vec![
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-			],
-			vec![
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-			],
-			vec![
-				// diagonal-Coin::Player1
-				vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
-			],
-			vec![
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::resign(Origin::signed(1), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.win_state, WinState::Player(Player::Two), "Player 2 should be the winner.");
+		assert_eq!(<AccountScoreCard<Test>>::get(2).won, 1);
+		assert_eq!(<AccountScoreCard<Test>>::get(1).lost, 1);
+	})
+}
+
+/// An account cannot resign from a game it isn't playing.
+#[test]
+fn cannot_resign_others_game() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::resign(Origin::signed(3), board_id), Error::<Test>::NotYourBoard);
+	})
+}
+
+/// An account cannot resign from a challenge that hasn't been accepted.
+#[test]
+fn cannot_resign_unaccepted_challenge() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_noop!(ConnectFour::resign(Origin::signed(2), board_id), Error::<Test>::ChallengeNotYetAccepted);
+	})
+}
+
+/// An account cannot resign from a game that has ended.
+#[test]
+fn cannot_resign_ended_game() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::resign(Origin::signed(1), board_id), Error::<Test>::GameEnded);
+	})
+}
+
+/// An account cannot resign from a game that doesn't exist.
+#[test]
+fn cannot_resign_non_existent_game() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(ConnectFour::resign(Origin::signed(1), H256::repeat_byte(1)), Error::<Test>::GameDoesNotExist);
+	})
+}
+
+/// A player who doesn't move in time forfeits the game.
+#[test]
+fn game_times_out_when_player_does_not_move() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		run_to_block(1 + MoveTimeout::get() - 1);
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.active, true, "The game should still be active");
+		run_to_block(1 + MoveTimeout::get());
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.active, false, "The game should no longer be active");
+		assert_eq!(board.win_state, WinState::Player(Player::Two), "Player 2 should be the winner.");
+		assert_eq!(<Challenges<Test>>::contains_key(1, 2), false);
+		assert_eq!(<AccountScoreCard<Test>>::get(1).lost, 1);
+		assert_eq!(<AccountScoreCard<Test>>::get(2).won, 1);
+	})
+}
+
+/// Making a move restarts the clock for the opponent.
+#[test]
+fn move_resets_the_timeout() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		run_to_block(5);
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		// the original deadline passes without effect.
+		run_to_block(1 + MoveTimeout::get());
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.active, true, "The game should still be active");
+		assert_eq!(board.last_move_at, 5);
+		// player 2 hasn't moved since.
+		run_to_block(5 + MoveTimeout::get());
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.active, false, "The game should no longer be active");
+		assert_eq!(board.win_state, WinState::Player(Player::One), "Player 1 should be the winner.");
+	})
+}
+
+/// Starts a game between accounts 1 and 2 played on a clock, returning its board.
+fn start_clock_game(bank: u64, increment: u64) -> H256 {
+	let settings = GameSettings { time_control: Some(TimeControl { bank, increment }), ..Default::default() };
+	assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+	let board_id = board_between(1, 2);
+	assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+	board_id
+}
+
+/// The blocks a player takes over a move are taken off their clock, and the increment added.
+#[test]
+fn moves_are_charged_to_the_clock() {
+	new_test_ext().execute_with(|| {
+		let board_id = start_clock_game(10, 2);
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.clock, Some(Clock { player_1: 10, player_2: 10 }));
+		run_to_block(4);
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.clock, Some(Clock { player_1: 9, player_2: 10 }));
+		run_to_block(5);
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.clock, Some(Clock { player_1: 9, player_2: 11 }));
+	})
+}
+
+/// Taking back a move gives the mover back the time they spent on it, without the increment.
+#[test]
+fn takeback_restores_the_clock() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings {
+			time_control: Some(TimeControl { bank: 10, increment: 2 }),
+			rated: false,
+			..Default::default()
+		};
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		run_to_block(4);
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		run_to_block(8);
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.clock, Some(Clock { player_1: 9, player_2: 8 }));
+		assert_ok!(ConnectFour::request_takeback(Origin::signed(2), board_id));
+		run_to_block(9);
+		assert_ok!(ConnectFour::accept_takeback(Origin::signed(1), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.clock, Some(Clock { player_1: 9, player_2: 10 }));
+		// player 2's clock starts again from the takeback.
+		run_to_block(10);
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.clock, Some(Clock { player_1: 9, player_2: 11 }));
+	})
+}
+
+/// A player who moves after running out of time loses the game instead.
+#[test]
+fn moving_after_the_flag_falls_loses() {
+	new_test_ext().execute_with(|| {
+		let board_id = start_clock_game(5, 0);
+		run_to_block(7);
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		System::assert_has_event(crate::Event::<Test>::GameTimedOut(1, board_id).into());
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.active, false, "The game should no longer be active");
+		assert_eq!(board.win_state, WinState::Player(Player::Two), "Player 2 should be the winner.");
+		assert_eq!(ConnectFour::get_move_history(board_id).len(), 0, "The move shouldn't be played");
+		assert_eq!(<AccountScoreCard<Test>>::get(1).lost, 1);
+	})
+}
+
+/// The opponent of a player who has run out of time can claim the win.
+#[test]
+fn claim_timeout_when_opponent_runs_out_of_time() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(3), 4, GameSettings::default(), None));
+		let untimed = board_between(3, 4);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(4), untimed));
+		assert_noop!(ConnectFour::claim_timeout(Origin::signed(4), untimed), Error::<Test>::NoTimeControl);
+		let board_id = start_clock_game(5, 0);
+		run_to_block(6);
+		assert_noop!(ConnectFour::claim_timeout(Origin::signed(2), board_id), Error::<Test>::OpponentHasTimeLeft);
+		run_to_block(7);
+		// player 1 is to move, so they can't claim the win.
+		assert_noop!(ConnectFour::claim_timeout(Origin::signed(1), board_id), Error::<Test>::OpponentHasTimeLeft);
+		assert_noop!(ConnectFour::claim_timeout(Origin::signed(5), board_id), Error::<Test>::NotYourBoard);
+		assert_ok!(ConnectFour::claim_timeout(Origin::signed(2), board_id));
+		System::assert_has_event(crate::Event::<Test>::GameTimedOut(1, board_id).into());
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.win_state, WinState::Player(Player::Two), "Player 2 should be the winner.");
+		assert_noop!(ConnectFour::claim_timeout(Origin::signed(2), board_id), Error::<Test>::GameEnded);
+	})
+}
+
+/// A game played on a clock isn't timed out by the move timeout.
+#[test]
+fn clock_game_does_not_use_the_move_timeout() {
+	new_test_ext().execute_with(|| {
+		let board_id = start_clock_game(100, 0);
+		run_to_block(1 + MoveTimeout::get());
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.active, true, "The game should still be active");
+	})
+}
+
+/// A challenge that hasn't been accepted is not timed out.
+#[test]
+fn unaccepted_challenge_does_not_time_out() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		run_to_block(1 + MoveTimeout::get());
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.win_state, WinState::Ongoing);
+		assert_eq!(<Challenges<Test>>::contains_key(1, 2), true);
+	})
+}
+
+/// An account can challenge another to a game on a bigger board.
+#[test]
+fn can_challenge_with_custom_board() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { rows: 7, columns: 8, win_length: 5, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.settings, settings);
+		let state = board.grid().unwrap();
+		assert_eq!(state.len(), 7);
+		assert!(state.iter().all(|row| row.len() == 8));
+	})
+}
+
+/// An account cannot challenge with a board that is out of bounds, or cannot be won.
+#[test]
+fn cannot_challenge_with_invalid_settings() {
+	new_test_ext().execute_with(|| {
+		let invalid_settings = [
+			GameSettings { rows: MaxRows::get() as u8 + 1, columns: 7, win_length: 4, ..Default::default() },
+			GameSettings { rows: 6, columns: MaxColumns::get() as u8 + 1, win_length: 4, ..Default::default() },
+			GameSettings { rows: 6, columns: 7, win_length: 2, ..Default::default() },
+			GameSettings { rows: 4, columns: 7, win_length: 5, ..Default::default() },
+		];
+		for settings in invalid_settings {
+			assert_noop!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None), Error::<Test>::InvalidGameSettings);
+		}
+	})
+}
+
+/// A winning line can't be longer than `MAX_WIN_LENGTH`, however large the configured bounds.
+#[test]
+fn win_length_is_bounded() {
+	let line = MAX_WIN_LENGTH as u8;
+	let settings = GameSettings { rows: line, columns: line, win_length: line, ..Default::default() };
+	assert!(settings.is_valid(u32::MAX, u32::MAX));
+	let settings = GameSettings { rows: line + 1, columns: line + 1, win_length: line + 1, ..Default::default() };
+	assert!(!settings.is_valid(u32::MAX, u32::MAX));
+}
+
+/// Columns beyond the board's width are invalid.
+#[test]
+fn cannot_play_outside_custom_board() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { rows: 7, columns: 8, win_length: 5, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::play(Origin::signed(1), board_id, 8), Error::<Test>::InvalidColumnForMove);
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 7));
+	})
+}
+
+/// A connect-5 game is not won with four in a row.
+#[test]
+fn can_win_connect_five_game() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { rows: 7, columns: 8, win_length: 5, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		for _ in 0..4 {
+			assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+			assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		}
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.active, true, "Four in a row should not win a connect-5 game");
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.active, false, "The game should no longer be active");
+		assert_eq!(board.win_state, WinState::Player(Player::One), "Player 1 should be the winner.");
+	})
+}
+
+/// A player can pop their own coin out of the bottom of a column in a PopOut game.
+#[test]
+fn can_pop_own_coin() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { variant: GameVariant::PopOut, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 0));
+		assert_ok!(ConnectFour::pop(Origin::signed(1), board_id, 0));
+		// the column shifted down.
+		let state = ConnectFour::get_connect_four_board_by_id(board_id).unwrap().grid().unwrap();
+		assert_eq!(state[0][0], Coin::Player2);
+		assert_eq!(state[1][0], Coin::Empty);
+		// it is now player 2's turn.
+		assert_noop!(ConnectFour::play(Origin::signed(1), board_id, 1), Error::<Test>::NotYourMove);
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+	})
+}
+
+/// A player cannot pop a coin that isn't theirs, or from an empty column.
+#[test]
+fn cannot_pop_opponents_coin() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { variant: GameVariant::PopOut, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_noop!(ConnectFour::pop(Origin::signed(2), board_id, 0), Error::<Test>::NotYourCoin);
+		assert_noop!(ConnectFour::pop(Origin::signed(2), board_id, 1), Error::<Test>::NotYourCoin);
+		assert_noop!(ConnectFour::pop(Origin::signed(2), board_id, 7), Error::<Test>::InvalidColumnForMove);
+		assert_noop!(ConnectFour::pop(Origin::signed(1), board_id, 0), Error::<Test>::NotYourMove);
+		assert_noop!(ConnectFour::pop(Origin::signed(3), board_id, 0), Error::<Test>::NotYourBoard);
+	})
+}
+
+/// Coins cannot be popped in a standard game.
+#[test]
+fn cannot_pop_in_standard_game() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		assert_noop!(ConnectFour::pop(Origin::signed(1), board_id, 0), Error::<Test>::PopNotAllowed);
+	})
+}
+
+/// A full PopOut board is not a draw while the player to move can pop a coin.
+#[test]
+fn pop_out_full_board_is_playable() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { variant: GameVariant::PopOut, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		let mut board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		let draw_state = vec![
+			vec![Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1],
+			vec![Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player2, Coin::Player2, Coin::Player2, Coin::Player1],
+			vec![Coin::Player1, Coin::Player2, Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player2, Coin::Player2],
+			vec![Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player1],
+			vec![Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player1],
+			vec![Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1],
+		];
+		board.set_state(draw_state);
+		assert_eq!(board.get_winner(), WinState::Ongoing);
+	})
+}
+
+/// check win states reached by popping a coin, with the popper (player 1) to move.
+#[test]
+fn check_pop_out_win_states() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { variant: GameVariant::PopOut, ..Default::default() };
+		let popping_states = [
+			(
+				// popping completes lines for both players, the popper wins
+				vec![
+					vec![Coin::Player1, Coin::Player2, Coin::Player2, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				],
+				0,
+				WinState::Player(Player::One),
+			),
+			(
+				// popping completes a line for the opponent only
+				vec![
+					vec![Coin::Player1, Coin::Player2, Coin::Player2, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				],
+				0,
+				WinState::Player(Player::Two),
+			),
+			(
+				// popping completes a line for the popper
+				vec![
+					vec![Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				],
+				0,
+				WinState::Player(Player::One),
+			),
+			(
+				// popping a coin in the middle of the board
+				vec![
+					vec![Coin::Player2, Coin::Player2, Coin::Player2, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				],
+				3,
+				WinState::Player(Player::Two),
+			),
+			(
+				// popping that doesn't complete any line
+				vec![
+					vec![Coin::Player1, Coin::Player2, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Player2, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+					vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				],
+				0,
+				WinState::Ongoing,
+			),
+		];
+		for (i, (state, column, expected)) in popping_states.into_iter().enumerate() {
+			// play every state against a different opponent.
+			let opponent = 10 + i as u64;
+			assert_ok!(ConnectFour::challenge_player(Origin::signed(1), opponent, settings, None));
+			let board_id = board_between(1, opponent);
+			assert_ok!(ConnectFour::accept_challenge(Origin::signed(opponent), board_id));
+			let mut board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+			board.set_state(state);
+			<ConnectFourBoardById<Test>>::insert(board_id, board);
+			assert_ok!(ConnectFour::pop(Origin::signed(1), board_id, column));
+			let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+			assert_eq!(board.win_state, expected, "Unexpected result after popping column {column}");
+		}
+	})
+}
+
+/// Accounts start with the initial rating.
+#[test]
+fn accounts_start_with_initial_rating() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(ConnectFour::get_rating(1), InitialRating::get());
+	})
+}
+
+/// The winner of a game between equally rated players takes half of the K-factor.
+#[test]
+fn winning_a_game_updates_ratings() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		for _ in 0..3 {
+			assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+			assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		}
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_eq!(ConnectFour::get_rating(1), InitialRating::get() + RatingKFactor::get() / 2);
+		assert_eq!(ConnectFour::get_rating(2), InitialRating::get() - RatingKFactor::get() / 2);
+	})
+}
+
+/// Beating a much stronger player earns more points than beating an equal.
+#[test]
+fn upset_win_earns_more_rating() {
+	new_test_ext().execute_with(|| {
+		<AccountRating<Test>>::insert(1, 1300);
+		<AccountRating<Test>>::insert(2, 1700);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		assert_eq!(ConnectFour::get_rating(1), 1329);
+		assert_eq!(ConnectFour::get_rating(2), 1671);
+	})
+}
+
+/// The expected score follows the Elo curve.
+#[test]
+fn check_expected_scores() {
+	assert_eq!(expected_score(1500, 1500), SCORE_SCALE / 2);
+	// a 400 point lead is expected to score 10 times as much as the opponent.
+	assert_eq!(expected_score(1900, 1500), 909_091);
+	assert_eq!(expected_score(1500, 1900), 90_909);
+	// interpolated between samples, and both expected scores add up to a whole point.
+	for difference in [10, 137, 399, 801, 2000] {
+		let stronger = expected_score(1500 + difference, 1500);
+		let weaker = expected_score(1500, 1500 + difference);
+		assert!(stronger > SCORE_SCALE / 2);
+		assert_eq!(stronger + weaker, SCORE_SCALE);
+	}
+	// differences are capped at 800 points.
+	assert_eq!(expected_score(1500, 2300), expected_score(1500, 3000));
+}
+
+/// A draw moves the ratings towards each other, and ratings never go below zero.
+#[test]
+fn check_new_ratings() {
+	assert_eq!(new_rating(1500, 1500, SCORE_SCALE / 2, 32), 1500);
+	assert_eq!(new_rating(1700, 1300, SCORE_SCALE / 2, 32), 1687);
+	assert_eq!(new_rating(1300, 1700, SCORE_SCALE / 2, 32), 1313);
+	assert_eq!(new_rating(10, 1500, 0, 32), 10);
+	assert_eq!(new_rating(0, 0, 0, 32), 0);
+}
+
+/// checks draw status and points
+#[test]
+fn can_draw_a_game() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		// get board_id before starting
+		let mut board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		// set the board state to a definite draw.
+		let draw_state = vec![
+			vec![Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1],
+			vec![Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player2, Coin::Player2, Coin::Player2, Coin::Player1],
+			vec![Coin::Player1, Coin::Player2, Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player2, Coin::Player2],
+			vec![Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player1],
+			vec![Coin::Player2, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player1],
+			vec![Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1, Coin::Player2, Coin::Player1],
+		];
+		board.set_state(draw_state);
+		board.active = false;
+		let winstate = board.get_winner();
+		assert_eq!(winstate, WinState::Draw);
+	})
+}
+/// check several possible win-states
+#[test]
+fn check_win_states() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		// get board_id before starting
+		let mut board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		let winning_states = [
+			vec![
+				vec![Coin::Player1, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+			],
+			vec![
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Player2, Coin::Player2, Coin::Player2, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+			],
+			vec![
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Player1, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+			],
+			vec![
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Player2, Coin::Player2, Coin::Player2],
+			],
+			vec![
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Player1, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+			],
+			vec![
+				vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+			],
+			vec![
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+			],
+			vec![
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Player2, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+			],
+			vec![
+				// diagonal-Coin::Player1
+				vec![Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+				vec![Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
+			],
+			vec![
 				// diagonal-Coin::Player1
 				vec![Coin::Empty, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
 				vec![Coin::Empty, Coin::Empty, Coin::Player1, Coin::Empty, Coin::Empty, Coin::Empty, Coin::Empty],
@@ -435,3 +1435,1289 @@ fn check_win_states() {
 	})
 }
 
+/// A player can offer a draw, and the game is drawn when the opponent accepts it.
+#[test]
+fn can_agree_to_a_draw() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::offer_draw(Origin::signed(1), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.draw_offer, Some(Player::One));
+		assert_ok!(ConnectFour::accept_draw(Origin::signed(2), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.active, false);
+		assert_eq!(board.win_state, WinState::Draw);
+		assert_eq!(board.draw_offer, None);
+		System::assert_has_event(crate::Event::<Test>::GameDrawn(board_id).into());
+		for player in [1, 2] {
+			let score_card = <AccountScoreCard<Test>>::get(player);
+			assert_eq!(score_card.draw, 1);
+			assert_eq!(score_card.points, PointsForDraw::get() as i64);
+		}
+		assert_eq!(<Challenges<Test>>::contains_key(1, 2), false);
+		assert_noop!(ConnectFour::play(Origin::signed(2), board_id, 0), Error::<Test>::GameEnded);
+	})
+}
+
+/// The opponent can decline a draw offer, and the game goes on.
+#[test]
+fn can_decline_a_draw() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::offer_draw(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::decline_draw(Origin::signed(1), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.draw_offer, None);
+		assert_eq!(board.active, true);
+		assert_noop!(ConnectFour::accept_draw(Origin::signed(1), board_id), Error::<Test>::NoDrawOffer);
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+	})
+}
+
+/// A move lapses a pending draw offer.
+#[test]
+fn move_clears_draw_offer() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::offer_draw(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.draw_offer, None);
+		assert_noop!(ConnectFour::accept_draw(Origin::signed(1), board_id), Error::<Test>::NoDrawOffer);
+	})
+}
+
+/// Only the opponent of the player who offered a draw can answer it, and only one offer
+/// can be pending.
+#[test]
+fn cannot_answer_own_draw_offer() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_noop!(ConnectFour::offer_draw(Origin::signed(1), board_id), Error::<Test>::ChallengeNotYetAccepted);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::offer_draw(Origin::signed(3), board_id), Error::<Test>::NotYourBoard);
+		assert_ok!(ConnectFour::offer_draw(Origin::signed(1), board_id));
+		assert_noop!(ConnectFour::offer_draw(Origin::signed(2), board_id), Error::<Test>::DrawAlreadyOffered);
+		assert_noop!(ConnectFour::accept_draw(Origin::signed(1), board_id), Error::<Test>::CannotAnswerYourOwnDrawOffer);
+		assert_noop!(ConnectFour::decline_draw(Origin::signed(1), board_id), Error::<Test>::CannotAnswerYourOwnDrawOffer);
+		assert_noop!(ConnectFour::accept_draw(Origin::signed(3), board_id), Error::<Test>::NotYourBoard);
+	})
+}
+
+/// A player can take back their last move in an unrated game, once their opponent agrees.
+#[test]
+fn can_take_back_a_move() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { rated: false, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 3));
+		// only the player who made the last move can take it back.
+		assert_noop!(ConnectFour::request_takeback(Origin::signed(1), board_id), Error::<Test>::NotYourLastMove);
+		assert_ok!(ConnectFour::request_takeback(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::request_takeback(Origin::signed(2), board_id), Error::<Test>::TakebackAlreadyRequested);
+		assert_noop!(ConnectFour::accept_takeback(Origin::signed(2), board_id), Error::<Test>::CannotAcceptYourOwnTakeback);
+		run_to_block(3);
+		assert_ok!(ConnectFour::accept_takeback(Origin::signed(1), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		let state = board.grid().unwrap();
+		assert_eq!(state[0][0], Coin::Player1);
+		assert_eq!(state[0][3], Coin::Empty);
+		assert_eq!(board.takeback_request, None);
+		assert_eq!(board.player_to_move(), Player::Two);
+		assert_eq!(board.last_move_at, 3);
+		assert_eq!(ConnectFour::get_move_history(board_id).len(), 1);
+		System::assert_has_event(crate::Event::<Test>::TakebackAccepted(1, board_id).into());
+		// it's player 2's move again.
+		assert_noop!(ConnectFour::play(Origin::signed(1), board_id, 1), Error::<Test>::NotYourMove);
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 4));
+	})
+}
+
+/// The first move of a game can be taken back too.
+#[test]
+fn can_take_back_first_move() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { rated: false, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::request_takeback(Origin::signed(1), board_id), Error::<Test>::NotYourLastMove);
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::request_takeback(Origin::signed(1), board_id));
+		assert_ok!(ConnectFour::accept_takeback(Origin::signed(2), board_id));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.state, Some(Bitboards::new(7)));
+		assert!(ConnectFour::get_move_history(board_id).is_empty());
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 1));
+	})
+}
+
+/// A takeback request lapses when the opponent moves instead.
+#[test]
+fn move_clears_takeback_request() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { rated: false, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::request_takeback(Origin::signed(1), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 0));
+		assert_noop!(ConnectFour::accept_takeback(Origin::signed(2), board_id), Error::<Test>::NoTakebackRequest);
+	})
+}
+
+/// Takebacks are switched off for rated games, and for finished games.
+#[test]
+fn cannot_take_back_in_rated_or_finished_games() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_noop!(ConnectFour::request_takeback(Origin::signed(1), board_id), Error::<Test>::TakebacksNotAllowed);
+		let settings = GameSettings { rated: false, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::request_takeback(Origin::signed(1), board_id), Error::<Test>::GameEnded);
+	})
+}
+
+/// The result of an unrated game doesn't change the players' ratings.
+#[test]
+fn unrated_game_does_not_change_ratings() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { rated: false, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		assert_eq!(ConnectFour::get_rating(1), InitialRating::get());
+		assert_eq!(ConnectFour::get_rating(2), InitialRating::get());
+		assert_eq!(<AccountScoreCard<Test>>::get(1).won, 1);
+	})
+}
+
+/// Both players stake on a game when it is accepted, and the winner takes the pot minus
+/// the house fee.
+#[test]
+fn winner_takes_the_pot() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), Some(100)));
+		let board_id = board_between(1, 2);
+		assert_eq!(ConnectFour::get_stake(board_id), Some(100));
+		assert_eq!(Balances::reserved_balance(1), 0);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		System::assert_has_event(crate::Event::<Test>::StakesReserved(board_id, 100).into());
+		for player in [1, 2] {
+			assert_eq!(Balances::reserved_balance(player), 100);
+			assert_eq!(Balances::free_balance(player), INITIAL_BALANCE - 100);
+		}
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		System::assert_has_event(crate::Event::<Test>::PotWon(1, board_id, 180).into());
+		assert_eq!(Balances::free_balance(1), INITIAL_BALANCE + 90);
+		assert_eq!(Balances::free_balance(2), INITIAL_BALANCE - 100);
+		assert_eq!(Balances::free_balance(HOUSE), HOUSE_BALANCE + 20);
+		for player in [1, 2] {
+			assert_eq!(Balances::reserved_balance(player), 0);
+		}
+		assert_eq!(ConnectFour::get_stake(board_id), None);
+	})
+}
+
+/// The house fee stays with the players when the house account doesn't exist.
+#[test]
+fn fee_is_not_taken_without_a_house_account() {
+	new_test_ext().execute_with(|| {
+		use frame_support::traits::Currency;
+		Balances::make_free_balance_be(&HOUSE, 0);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), Some(100)));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		assert_eq!(Balances::free_balance(1), INITIAL_BALANCE + 90);
+		assert_eq!(Balances::free_balance(2), INITIAL_BALANCE - 90);
+		assert_eq!(Balances::free_balance(HOUSE), 0);
+		for player in [1, 2] {
+			assert_eq!(Balances::reserved_balance(player), 0);
+		}
+	})
+}
+
+/// The pot of a drawn game is split between the players.
+#[test]
+fn drawn_game_splits_the_pot() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), Some(100)));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::offer_draw(Origin::signed(1), board_id));
+		assert_ok!(ConnectFour::accept_draw(Origin::signed(2), board_id));
+		System::assert_has_event(crate::Event::<Test>::PotSplit(board_id, 90).into());
+		for player in [1, 2] {
+			assert_eq!(Balances::free_balance(player), INITIAL_BALANCE - 10);
+			assert_eq!(Balances::reserved_balance(player), 0);
+		}
+		assert_eq!(Balances::free_balance(HOUSE), HOUSE_BALANCE + 20);
+	})
+}
+
+/// Nothing is staked on a challenge that is declined or withdrawn, and a challenge can't be
+/// accepted unless both players can afford the stake.
+#[test]
+fn stakes_of_cancelled_challenges_are_dropped() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), Some(100)));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::decline_challenge(Origin::signed(2), board_id));
+		assert_eq!(ConnectFour::get_stake(board_id), None);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), Some(INITIAL_BALANCE * 2)));
+		let board_id = board_between(1, 2);
+		assert_noop!(ConnectFour::accept_challenge(Origin::signed(2), board_id), Error::<Test>::CannotAffordStake);
+		assert_ok!(ConnectFour::withdraw_challenge(Origin::signed(1), board_id));
+		assert_eq!(ConnectFour::get_stake(board_id), None);
+		for player in [1, 2] {
+			assert_eq!(Balances::free_balance(player), INITIAL_BALANCE);
+		}
+	})
+}
+
+/// The ID of the only tournament created in a test.
+fn only_tournament() -> H256 {
+	<Tournaments<Test>>::iter_keys().next().unwrap()
+}
+
+/// The board of a match in the current round of a tournament.
+fn tournament_board(tournament_id: H256, index: usize) -> H256 {
+	ConnectFour::get_tournament(tournament_id).unwrap().matches[index].board_id.unwrap()
+}
+
+/// Tournaments, leagues and Swiss tournaments created in the same block get different IDs,
+/// derived from their creator, the nonce and the block.
+#[test]
+fn competitions_in_the_same_block_get_different_ids() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_tournament(Origin::signed(10), GameSettings::default(), 2));
+		assert_ok!(ConnectFour::create_tournament(Origin::signed(10), GameSettings::default(), 2));
+		assert_ok!(ConnectFour::create_swiss_tournament(Origin::signed(10), GameSettings::default(), 2, 1));
+		let expected = H256::from(sp_io::hashing::blake2_256(&(10u64, 0u64, 1u64).encode()));
+		assert!(ConnectFour::get_tournament(expected).is_some());
+		assert_eq!(<Tournaments<Test>>::iter_keys().count(), 2);
+		let expected = H256::from(sp_io::hashing::blake2_256(&(10u64, 2u64, 1u64).encode()));
+		assert!(ConnectFour::get_swiss_tournament(expected).is_some());
+	})
+}
+
+/// A competition is never created over an existing one, of any kind.
+#[test]
+fn cannot_create_a_competition_with_a_taken_id() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_tournament(Origin::signed(10), GameSettings::default(), 2));
+		let tournament = ConnectFour::get_tournament(only_tournament()).unwrap();
+		let taken = H256::from(sp_io::hashing::blake2_256(&(10u64, 1u64, 1u64).encode()));
+		<Tournaments<Test>>::insert(taken, tournament);
+		assert_noop!(
+			ConnectFour::create_league(Origin::signed(10), GameSettings::default(), 2),
+			Error::<Test>::CompetitionIdTaken
+		);
+	})
+}
+
+#[test]
+fn check_bracket_order() {
+	assert_eq!(bracket_order(1), vec![0]);
+	assert_eq!(bracket_order(2), vec![0, 1]);
+	assert_eq!(bracket_order(4), vec![0, 3, 1, 2]);
+	assert_eq!(bracket_order(8), vec![0, 7, 3, 4, 1, 6, 2, 5]);
+}
+
+/// The pairing decides who moves first in tournaments and leagues, so their settings can't
+/// choose the first move or bring in the swap rule.
+#[test]
+fn competitions_leave_the_first_move_to_the_pairing() {
+	new_test_ext().execute_with(|| {
+		for settings in [
+			GameSettings { first_move: FirstMove::Opponent, ..Default::default() },
+			GameSettings { first_move: FirstMove::Random, ..Default::default() },
+			GameSettings { swap_rule: true, ..Default::default() },
+		] {
+			assert_noop!(ConnectFour::create_tournament(Origin::signed(10), settings, 2), Error::<Test>::InvalidCompetitionSettings);
+			assert_noop!(ConnectFour::create_league(Origin::signed(10), settings, 2), Error::<Test>::InvalidCompetitionSettings);
+			assert_noop!(ConnectFour::create_swiss_tournament(Origin::signed(10), settings, 2, 1), Error::<Test>::InvalidCompetitionSettings);
+		}
+	})
+}
+
+/// Players register for a tournament until it's full or started, and only its creator can
+/// start it.
+#[test]
+fn can_register_for_a_tournament() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(ConnectFour::create_tournament(Origin::signed(1), GameSettings::default(), 1), Error::<Test>::InvalidTournamentSize);
+		assert_noop!(ConnectFour::create_tournament(Origin::signed(1), GameSettings::default(), MaxTournamentPlayers::get() + 1), Error::<Test>::InvalidTournamentSize);
+		assert_ok!(ConnectFour::create_tournament(Origin::signed(1), GameSettings::default(), 2));
+		let tournament_id = only_tournament();
+		assert_ok!(ConnectFour::join_tournament(Origin::signed(2), tournament_id));
+		assert_noop!(ConnectFour::join_tournament(Origin::signed(2), tournament_id), Error::<Test>::AlreadyRegistered);
+		assert_noop!(ConnectFour::start_tournament(Origin::signed(1), tournament_id), Error::<Test>::NotEnoughPlayers);
+		assert_ok!(ConnectFour::join_tournament(Origin::signed(3), tournament_id));
+		assert_noop!(ConnectFour::join_tournament(Origin::signed(4), tournament_id), Error::<Test>::TournamentFull);
+		assert_noop!(ConnectFour::start_tournament(Origin::signed(2), tournament_id), Error::<Test>::NotYourTournament);
+		assert_ok!(ConnectFour::start_tournament(Origin::signed(1), tournament_id));
+		assert_noop!(ConnectFour::start_tournament(Origin::signed(1), tournament_id), Error::<Test>::TournamentRegistrationClosed);
+		assert_noop!(ConnectFour::join_tournament(Origin::signed(4), tournament_id), Error::<Test>::TournamentRegistrationClosed);
+		let tournament = ConnectFour::get_tournament(tournament_id).unwrap();
+		assert_eq!(tournament.status, TournamentStatus::Running);
+		assert_eq!(tournament.round, 1);
+		assert_eq!(tournament.matches.len(), 1);
+	})
+}
+
+/// Players are seeded by rating, and the winners go through until one wins the final.
+#[test]
+fn can_play_a_tournament() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_tournament(Origin::signed(10), GameSettings::default(), 4));
+		let tournament_id = only_tournament();
+		for player in 1..=4 {
+			assert_ok!(ConnectFour::join_tournament(Origin::signed(player), tournament_id));
+			<AccountRating<Test>>::insert(player, 1400 + 50 * player as u32);
+		}
+		assert_ok!(ConnectFour::start_tournament(Origin::signed(10), tournament_id));
+		System::assert_has_event(crate::Event::<Test>::TournamentRoundStarted(tournament_id, 1).into());
+		let tournament = ConnectFour::get_tournament(tournament_id).unwrap();
+		assert_eq!(tournament.players, vec![4, 3, 2, 1]);
+		// the top seed plays the bottom seed.
+		let pairs: Vec<_> = tournament.matches.iter().map(|m| (m.player_1, m.player_2)).collect();
+		assert_eq!(pairs, vec![(4, Some(1)), (3, Some(2))]);
+		let board = ConnectFour::get_connect_four_board_by_id(tournament_board(tournament_id, 0)).unwrap();
+		assert_eq!(board.challenge_accepted, true);
+		assert_eq!(board.active, true);
+		// the lower seeds win their games.
+		assert_ok!(ConnectFour::resign(Origin::signed(4), tournament_board(tournament_id, 0)));
+		assert_noop!(ConnectFour::start_tournament_round(Origin::signed(7), tournament_id), Error::<Test>::RoundNotFinished);
+		assert_ok!(ConnectFour::resign(Origin::signed(3), tournament_board(tournament_id, 1)));
+		System::assert_last_event(crate::Event::<Test>::TournamentRoundFinished(tournament_id, 1).into());
+		assert_eq!(ConnectFour::get_tournament(tournament_id).unwrap().round, 1);
+		// anyone can start the next round.
+		assert_ok!(ConnectFour::start_tournament_round(Origin::signed(7), tournament_id));
+		System::assert_last_event(crate::Event::<Test>::TournamentRoundStarted(tournament_id, 2).into());
+		let tournament = ConnectFour::get_tournament(tournament_id).unwrap();
+		assert_eq!(tournament.round, 2);
+		assert_eq!(tournament.matches.len(), 1);
+		assert_eq!(tournament.matches[0].player_1, 1);
+		assert_eq!(tournament.matches[0].player_2, Some(2));
+		assert_ok!(ConnectFour::resign(Origin::signed(1), tournament_board(tournament_id, 0)));
+		System::assert_last_event(crate::Event::<Test>::TournamentWon(2, tournament_id).into());
+		assert_eq!(ConnectFour::get_tournament(tournament_id).unwrap().status, TournamentStatus::Finished);
+		assert_noop!(ConnectFour::start_tournament_round(Origin::signed(7), tournament_id), Error::<Test>::TournamentNotRunning);
+	})
+}
+
+/// The top seeds get byes when the number of players isn't a power of two.
+#[test]
+fn top_seeds_get_tournament_byes() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_tournament(Origin::signed(1), GameSettings::default(), 4));
+		let tournament_id = only_tournament();
+		for player in 1..=3 {
+			assert_ok!(ConnectFour::join_tournament(Origin::signed(player), tournament_id));
+		}
+		assert_ok!(ConnectFour::start_tournament(Origin::signed(1), tournament_id));
+		let tournament = ConnectFour::get_tournament(tournament_id).unwrap();
+		assert_eq!(tournament.matches[0].player_2, None);
+		assert_eq!(tournament.matches[0].board_id, None);
+		assert_eq!(tournament.matches[0].winner, Some(1));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), tournament_board(tournament_id, 1)));
+		assert_ok!(ConnectFour::start_tournament_round(Origin::signed(7), tournament_id));
+		let tournament = ConnectFour::get_tournament(tournament_id).unwrap();
+		assert_eq!(tournament.round, 2);
+		assert_eq!(tournament.matches[0].player_1, 1);
+		assert_eq!(tournament.matches[0].player_2, Some(3));
+	})
+}
+
+/// A drawn tournament game is replayed with the other player moving first.
+#[test]
+fn drawn_tournament_game_is_replayed() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_tournament(Origin::signed(1), GameSettings::default(), 2));
+		let tournament_id = only_tournament();
+		assert_ok!(ConnectFour::join_tournament(Origin::signed(1), tournament_id));
+		assert_ok!(ConnectFour::join_tournament(Origin::signed(2), tournament_id));
+		assert_ok!(ConnectFour::start_tournament(Origin::signed(1), tournament_id));
+		let board_id = tournament_board(tournament_id, 0);
+		assert_ok!(ConnectFour::offer_draw(Origin::signed(1), board_id));
+		assert_ok!(ConnectFour::accept_draw(Origin::signed(2), board_id));
+		let replay_id = tournament_board(tournament_id, 0);
+		assert_ne!(replay_id, board_id);
+		let replay = ConnectFour::get_connect_four_board_by_id(replay_id).unwrap();
+		assert_eq!((replay.player_1, replay.player_2), (2, 1));
+		assert_eq!(ConnectFour::get_tournament(tournament_id).unwrap().status, TournamentStatus::Running);
+		assert_ok!(ConnectFour::resign(Origin::signed(2), replay_id));
+		System::assert_last_event(crate::Event::<Test>::TournamentWon(1, tournament_id).into());
+	})
+}
+
+/// The ID of the only league created in a test.
+fn only_league() -> H256 {
+	<Leagues<Test>>::iter_keys().next().unwrap()
+}
+
+/// The ID of the board of the last fixture of a league.
+fn last_fixture(league_id: H256) -> H256 {
+	ConnectFour::get_league(league_id).unwrap().fixtures.last().unwrap().board_id
+}
+
+/// Over the rounds of a league, every player plays every other player twice, once at home,
+/// and plays at most once a round.
+#[test]
+fn check_league_rounds() {
+	assert_eq!(league_rounds(2), 2);
+	assert_eq!(league_rounds(3), 6);
+	assert_eq!(league_rounds(4), 6);
+	assert_eq!(league_round(3, 1), vec![(1, 2)]);
+	assert_eq!(league_round(3, 4), vec![(2, 1)]);
+	for players in 2..=7 {
+		let mut fixtures = Vec::new();
+		for round in 1..=league_rounds(players) {
+			let games = league_round(players, round);
+			assert_eq!(games.len(), players / 2);
+			let mut playing: Vec<_> = games.iter().flat_map(|&(home, away)| [home, away]).collect();
+			playing.sort();
+			playing.dedup();
+			assert_eq!(playing.len(), 2 * games.len());
+			fixtures.extend(games);
+		}
+		fixtures.sort();
+		let every_pair: Vec<_> = (0..players)
+			.flat_map(|home| (0..players).filter(move |&away| away != home).map(move |away| (home, away)))
+			.collect();
+		assert_eq!(fixtures, every_pair);
+	}
+}
+
+/// A league starts with the games of its first round.
+#[test]
+fn can_start_a_league() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(ConnectFour::create_league(Origin::signed(10), GameSettings::default(), 1), Error::<Test>::InvalidLeagueSize);
+		assert_noop!(ConnectFour::create_league(Origin::signed(10), GameSettings::default(), MaxLeaguePlayers::get() + 1), Error::<Test>::InvalidLeagueSize);
+		assert_ok!(ConnectFour::create_league(Origin::signed(10), GameSettings::default(), 3));
+		let league_id = only_league();
+		assert_ok!(ConnectFour::join_league(Origin::signed(1), league_id));
+		assert_noop!(ConnectFour::join_league(Origin::signed(1), league_id), Error::<Test>::AlreadyRegistered);
+		assert_noop!(ConnectFour::start_league(Origin::signed(10), league_id), Error::<Test>::NotEnoughPlayers);
+		assert_ok!(ConnectFour::join_league(Origin::signed(2), league_id));
+		assert_ok!(ConnectFour::join_league(Origin::signed(3), league_id));
+		assert_noop!(ConnectFour::join_league(Origin::signed(4), league_id), Error::<Test>::LeagueFull);
+		assert_noop!(ConnectFour::start_league(Origin::signed(1), league_id), Error::<Test>::NotYourLeague);
+		assert_ok!(ConnectFour::start_league(Origin::signed(10), league_id));
+		assert_noop!(ConnectFour::join_league(Origin::signed(4), league_id), Error::<Test>::LeagueRegistrationClosed);
+		System::assert_last_event(crate::Event::<Test>::LeagueStarted(league_id, 1 + LeagueSeasonLength::get()).into());
+		System::assert_has_event(crate::Event::<Test>::LeagueRoundStarted(league_id, 1).into());
+		let league = ConnectFour::get_league(league_id).unwrap();
+		assert_eq!(league.status, TournamentStatus::Running);
+		assert_eq!(league.round, 1);
+		let pairs: Vec<_> = league.fixtures.iter().map(|f| (f.home, f.away)).collect();
+		assert_eq!(pairs, vec![(2, 3)]);
+		for fixture in league.fixtures {
+			let board = ConnectFour::get_connect_four_board_by_id(fixture.board_id).unwrap();
+			assert_eq!(board.player_1, fixture.home);
+			assert_eq!(board.active, true);
+		}
+	})
+}
+
+/// Anyone can start the next round of a league once every game of the current round has
+/// ended, until every round has been played.
+#[test]
+fn league_rounds_start_when_the_last_one_has_ended() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_league(Origin::signed(10), GameSettings::default(), 4));
+		let league_id = only_league();
+		assert_noop!(ConnectFour::start_league_round(Origin::signed(7), league_id), Error::<Test>::LeagueNotRunning);
+		for player in 1..=4 {
+			assert_ok!(ConnectFour::join_league(Origin::signed(player), league_id));
+		}
+		assert_ok!(ConnectFour::start_league(Origin::signed(10), league_id));
+		for round in 1..=league_rounds(4) {
+			let league = ConnectFour::get_league(league_id).unwrap();
+			assert_eq!(league.round, round);
+			assert_eq!(league.fixtures.len(), 2 * round as usize);
+			let (first, second) = (league.fixtures[league.fixtures.len() - 2].clone(), league.fixtures[league.fixtures.len() - 1].clone());
+			assert_ok!(ConnectFour::resign(Origin::signed(first.away), first.board_id));
+			if round < league_rounds(4) {
+				assert_noop!(ConnectFour::start_league_round(Origin::signed(7), league_id), Error::<Test>::RoundNotFinished);
+			}
+			assert_ok!(ConnectFour::resign(Origin::signed(second.away), second.board_id));
+			if round < league_rounds(4) {
+				assert_ok!(ConnectFour::start_league_round(Origin::signed(7), league_id));
+				System::assert_last_event(crate::Event::<Test>::LeagueRoundStarted(league_id, round + 1).into());
+			}
+		}
+		assert_noop!(ConnectFour::start_league_round(Origin::signed(7), league_id), Error::<Test>::NoRoundsLeft);
+		let mut pairs: Vec<_> = ConnectFour::get_league(league_id).unwrap().fixtures.iter().map(|f| (f.home, f.away)).collect();
+		pairs.sort();
+		assert_eq!(pairs.len(), 12);
+		pairs.dedup();
+		assert_eq!(pairs.len(), 12);
+		for player in 1..=4 {
+			assert_eq!(ConnectFour::get_league_standing(league_id, player).won, 3);
+		}
+	})
+}
+
+/// The standings of a league are kept as its games end, and the final ranking breaks ties
+/// on points by the results between the players level on points.
+#[test]
+fn league_season_closes_with_ranking() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_league(Origin::signed(10), GameSettings::default(), 3));
+		let league_id = only_league();
+		for player in 1..=3 {
+			assert_ok!(ConnectFour::join_league(Origin::signed(player), league_id));
+		}
+		assert_ok!(ConnectFour::start_league(Origin::signed(10), league_id));
+		// 1 beats 2 at home, and draws away. 3 beats 1 twice, and splits the games with 2.
+		// 2 beats 3 at home.
+		assert_ok!(ConnectFour::resign(Origin::signed(3), last_fixture(league_id)));
+		assert_ok!(ConnectFour::start_league_round(Origin::signed(7), league_id));
+		// 2 and 1 draw.
+		assert_ok!(ConnectFour::offer_draw(Origin::signed(2), last_fixture(league_id)));
+		assert_ok!(ConnectFour::accept_draw(Origin::signed(1), last_fixture(league_id)));
+		assert_ok!(ConnectFour::start_league_round(Origin::signed(7), league_id));
+		// 3 beats 1 away.
+		assert_ok!(ConnectFour::resign(Origin::signed(1), last_fixture(league_id)));
+		assert_ok!(ConnectFour::start_league_round(Origin::signed(7), league_id));
+		// 3 beats 2 at home.
+		assert_ok!(ConnectFour::resign(Origin::signed(2), last_fixture(league_id)));
+		assert_ok!(ConnectFour::start_league_round(Origin::signed(7), league_id));
+		// 1 beats 2 at home.
+		assert_ok!(ConnectFour::resign(Origin::signed(2), last_fixture(league_id)));
+		assert_ok!(ConnectFour::start_league_round(Origin::signed(7), league_id));
+		// 3 beats 1 at home.
+		assert_ok!(ConnectFour::resign(Origin::signed(1), last_fixture(league_id)));
+		let league = ConnectFour::get_league(league_id).unwrap();
+		let pairs: Vec<_> = league.fixtures.iter().map(|f| (f.home, f.away)).collect();
+		assert_eq!(pairs, vec![(2, 3), (2, 1), (1, 3), (3, 2), (1, 2), (3, 1)]);
+		assert_eq!(league.fixtures[4].result, WinState::Player(Player::One));
+		assert_eq!(league.fixtures[1].result, WinState::Draw);
+		let standing_3 = ConnectFour::get_league_standing(league_id, 3);
+		assert_eq!((standing_3.played, standing_3.won, standing_3.lost), (4, 3, 1));
+		for player in [1, 2] {
+			let standing = ConnectFour::get_league_standing(league_id, player);
+			assert_eq!((standing.won, standing.draw, standing.lost), (1, 1, 2));
+			assert_eq!(standing.points, (PointsForWin::get() + PointsForDraw::get()) as i64 - 2 * PointsForLoss::get() as i64);
+		}
+		run_to_block(1 + LeagueSeasonLength::get());
+		System::assert_has_event(crate::Event::<Test>::LeagueClosed(league_id, vec![3, 1, 2]).into());
+		let league = ConnectFour::get_league(league_id).unwrap();
+		assert_eq!(league.status, TournamentStatus::Finished);
+		assert_eq!(league.ranking, vec![3, 1, 2]);
+	})
+}
+
+/// Games that end after the season has closed don't count.
+#[test]
+fn league_games_after_the_season_do_not_count() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::create_league(Origin::signed(10), GameSettings::default(), 2));
+		let league_id = only_league();
+		assert_ok!(ConnectFour::join_league(Origin::signed(1), league_id));
+		assert_ok!(ConnectFour::join_league(Origin::signed(2), league_id));
+		assert_ok!(ConnectFour::start_league(Origin::signed(10), league_id));
+		run_to_block(1 + LeagueSeasonLength::get());
+		let board_id = ConnectFour::get_league(league_id).unwrap().fixtures[0].board_id;
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		assert_eq!(ConnectFour::get_league_standing(league_id, 1).played, 0);
+		assert_eq!(ConnectFour::get_league(league_id).unwrap().fixtures[0].result, WinState::Ongoing);
+		assert_noop!(ConnectFour::start_league_round(Origin::signed(7), league_id), Error::<Test>::LeagueNotRunning);
+	})
+}
+
+/// The ID of the only Swiss tournament created in a test.
+fn only_swiss_tournament() -> H256 {
+	<SwissTournaments<Test>>::iter_keys().next().unwrap()
+}
+
+/// Creates and starts a Swiss tournament between the given players.
+fn start_swiss_tournament(players: u64, rounds: u32) -> H256 {
+	assert_ok!(ConnectFour::create_swiss_tournament(Origin::signed(10), GameSettings::default(), players as u32, rounds));
+	let tournament_id = only_swiss_tournament();
+	for player in 1..=players {
+		assert_ok!(ConnectFour::join_swiss_tournament(Origin::signed(player), tournament_id));
+	}
+	assert_ok!(ConnectFour::start_swiss_tournament(Origin::signed(10), tournament_id));
+	tournament_id
+}
+
+/// The game of a player in the current round of a Swiss tournament.
+fn swiss_board(tournament_id: H256, player: u64) -> H256 {
+	let tournament = ConnectFour::get_swiss_tournament(tournament_id).unwrap();
+	tournament.games.iter().find(|game| game.player_1 == player || game.player_2 == player).unwrap().board_id
+}
+
+/// The players of each game of the current round of a Swiss tournament.
+fn swiss_pairings(tournament_id: H256) -> Vec<(u64, u64)> {
+	let tournament = ConnectFour::get_swiss_tournament(tournament_id).unwrap();
+	tournament.games.iter().map(|game| (game.player_1, game.player_2)).collect()
+}
+
+#[test]
+fn swiss_tournament_needs_fewer_rounds_than_players() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(ConnectFour::create_swiss_tournament(Origin::signed(10), GameSettings::default(), 4, 0), Error::<Test>::InvalidSwissRounds);
+		assert_noop!(ConnectFour::create_swiss_tournament(Origin::signed(10), GameSettings::default(), 4, 4), Error::<Test>::InvalidSwissRounds);
+		assert_ok!(ConnectFour::create_swiss_tournament(Origin::signed(10), GameSettings::default(), 4, 2));
+		let tournament_id = only_swiss_tournament();
+		assert_ok!(ConnectFour::join_swiss_tournament(Origin::signed(1), tournament_id));
+		assert_noop!(ConnectFour::join_swiss_tournament(Origin::signed(1), tournament_id), Error::<Test>::AlreadyRegistered);
+		assert_ok!(ConnectFour::join_swiss_tournament(Origin::signed(2), tournament_id));
+		assert_noop!(ConnectFour::start_swiss_tournament(Origin::signed(1), tournament_id), Error::<Test>::NotYourTournament);
+		assert_noop!(ConnectFour::start_swiss_tournament(Origin::signed(10), tournament_id), Error::<Test>::InvalidSwissRounds);
+		assert_ok!(ConnectFour::join_swiss_tournament(Origin::signed(3), tournament_id));
+		assert_ok!(ConnectFour::start_swiss_tournament(Origin::signed(10), tournament_id));
+		assert_noop!(ConnectFour::join_swiss_tournament(Origin::signed(4), tournament_id), Error::<Test>::TournamentRegistrationClosed);
+	})
+}
+
+/// Players with the same score are paired, and the final standings break ties on score
+/// with the Buchholz score.
+#[test]
+fn can_play_a_swiss_tournament() {
+	new_test_ext().execute_with(|| {
+		let tournament_id = start_swiss_tournament(4, 2);
+		System::assert_has_event(crate::Event::<Test>::SwissRoundStarted(tournament_id, 1).into());
+		assert_eq!(swiss_pairings(tournament_id), vec![(1, 2), (3, 4)]);
+		assert_ok!(ConnectFour::resign(Origin::signed(2), swiss_board(tournament_id, 1)));
+		assert_noop!(ConnectFour::start_swiss_round(Origin::signed(7), tournament_id), Error::<Test>::RoundNotFinished);
+		assert_eq!(ConnectFour::get_swiss_tournament(tournament_id).unwrap().round, 1);
+		assert_ok!(ConnectFour::resign(Origin::signed(4), swiss_board(tournament_id, 3)));
+		System::assert_last_event(crate::Event::<Test>::SwissRoundFinished(tournament_id, 1).into());
+		assert_ok!(ConnectFour::start_swiss_round(Origin::signed(7), tournament_id));
+		// the winners meet, and so do the losers.
+		System::assert_has_event(crate::Event::<Test>::SwissRoundStarted(tournament_id, 2).into());
+		assert_eq!(swiss_pairings(tournament_id), vec![(1, 3), (2, 4)]);
+		let board_id = swiss_board(tournament_id, 1);
+		assert_ok!(ConnectFour::offer_draw(Origin::signed(1), board_id));
+		assert_ok!(ConnectFour::accept_draw(Origin::signed(3), board_id));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), swiss_board(tournament_id, 2)));
+		// 1 and 3 both score 3, and 3 met the stronger opponents.
+		let tournament = ConnectFour::get_swiss_tournament(tournament_id).unwrap();
+		let scores: Vec<_> = tournament.players.iter().map(|player| player.score).collect();
+		assert_eq!(scores, vec![3, 0, 3, 2]);
+		assert_eq!(tournament.status, TournamentStatus::Finished);
+		assert_eq!(tournament.ranking, vec![3, 1, 4, 2]);
+		System::assert_last_event(crate::Event::<Test>::SwissTournamentFinished(tournament_id, vec![3, 1, 4, 2]).into());
+		assert_noop!(ConnectFour::start_swiss_round(Origin::signed(7), tournament_id), Error::<Test>::TournamentNotRunning);
+	})
+}
+
+/// A player gets at most one bye, and the player who has moved first less often moves first.
+#[test]
+fn swiss_pairings_give_byes_and_balance_colours() {
+	new_test_ext().execute_with(|| {
+		let tournament_id = start_swiss_tournament(3, 2);
+		// the lowest placed player gets the bye.
+		assert_eq!(swiss_pairings(tournament_id), vec![(1, 2)]);
+		let tournament = ConnectFour::get_swiss_tournament(tournament_id).unwrap();
+		assert_eq!(tournament.players[2].had_bye, true);
+		assert_eq!(tournament.players[2].score, 2);
+		assert_ok!(ConnectFour::resign(Origin::signed(2), swiss_board(tournament_id, 1)));
+		assert_ok!(ConnectFour::start_swiss_round(Origin::signed(7), tournament_id));
+		// 2 gets the bye this time, and 3 moves first against 1, who moved first already.
+		assert_eq!(swiss_pairings(tournament_id), vec![(3, 1)]);
+		assert_eq!(ConnectFour::get_swiss_tournament(tournament_id).unwrap().players[1].had_bye, true);
+	})
+}
+
+/// Players level on score who have already met aren't paired again.
+#[test]
+fn swiss_players_do_not_meet_twice() {
+	new_test_ext().execute_with(|| {
+		let tournament_id = start_swiss_tournament(4, 3);
+		for player in [1, 3] {
+			let board_id = swiss_board(tournament_id, player);
+			assert_ok!(ConnectFour::offer_draw(Origin::signed(player), board_id));
+			assert_ok!(ConnectFour::accept_draw(Origin::signed(player + 1), board_id));
+		}
+		assert_ok!(ConnectFour::start_swiss_round(Origin::signed(7), tournament_id));
+		// everyone is level, and 1 has already met 2.
+		assert_eq!(swiss_pairings(tournament_id), vec![(1, 3), (2, 4)]);
+	})
+}
+
+/// Swiss pairings avoid rematches whenever the round can be paired without them, and make
+/// as few as possible otherwise.
+#[test]
+fn check_swiss_pairings() {
+	let met_in = |games: Vec<(usize, usize)>| move |a: usize, b: usize| games.contains(&(a, b)) || games.contains(&(b, a));
+	// pairing 0 with 1 and 2 with 3 would leave 4 and 5, who have met.
+	let met = met_in(vec![(0, 2), (1, 3), (4, 5)]);
+	assert_eq!(pair_swiss_players(&[0, 1, 2, 3, 4, 5], &met), vec![(0, 1), (2, 4), (3, 5)]);
+	// 0, 2 and 4 have met 1, 3 and 5, and one rematch is unavoidable.
+	let met = met_in((0..3).flat_map(|a| (0..3).map(move |b| (2 * a, 2 * b + 1))).collect());
+	assert_eq!(pair_swiss_players(&[0, 1, 2, 3, 4, 5], &met), vec![(0, 2), (1, 3), (4, 5)]);
+	let met = met_in(vec![(0, 1)]);
+	assert_eq!(pair_swiss_players(&[0, 1], &met), vec![(0, 1)]);
+	assert_eq!(pair_swiss_players(&[], &met), vec![]);
+}
+
+/// A search for the pairings that runs out of steps pairs each player with the next player
+/// they haven't met. With an odd number of players, no pairing has all of them, so every
+/// search fails.
+#[test]
+fn swiss_pairings_give_up_after_max_pairing_steps() {
+	let order: Vec<usize> = (0..21).collect();
+	let met = |a: usize, b: usize| (a, b) == (0, 1);
+	let pairs = pair_swiss_players(&order, met);
+	assert_eq!(pairs.len(), 10);
+	assert_eq!(pairs[..2], [(0, 2), (1, 3)]);
+	assert_eq!(pairs[9], (18, 19));
+}
+
+/// A player isn't paired with the next player they haven't met when that would force a
+/// rematch further down.
+#[test]
+fn swiss_pairings_look_ahead_to_avoid_rematches() {
+	new_test_ext().execute_with(|| {
+		let tournament_id = start_swiss_tournament(6, 3);
+		assert_eq!(swiss_pairings(tournament_id), vec![(1, 2), (3, 4), (5, 6)]);
+		for player in [1, 3, 5] {
+			let board_id = swiss_board(tournament_id, player);
+			assert_ok!(ConnectFour::offer_draw(Origin::signed(player), board_id));
+			assert_ok!(ConnectFour::accept_draw(Origin::signed(player + 1), board_id));
+		}
+		assert_ok!(ConnectFour::start_swiss_round(Origin::signed(7), tournament_id));
+		// pairing 2 with 4 would leave 5 and 6, who have met.
+		assert_eq!(swiss_pairings(tournament_id), vec![(1, 3), (2, 5), (4, 6)]);
+	})
+}
+
+/// Players meet again when the round can't be paired otherwise, in as few games as possible.
+#[test]
+fn swiss_players_meet_again_when_unavoidable() {
+	new_test_ext().execute_with(|| {
+		let tournament_id = start_swiss_tournament(6, 5);
+		// 1 and 4 win, 5 and 6 draw.
+		assert_ok!(ConnectFour::resign(Origin::signed(2), swiss_board(tournament_id, 1)));
+		assert_ok!(ConnectFour::resign(Origin::signed(3), swiss_board(tournament_id, 4)));
+		let board_id = swiss_board(tournament_id, 5);
+		assert_ok!(ConnectFour::offer_draw(Origin::signed(5), board_id));
+		assert_ok!(ConnectFour::accept_draw(Origin::signed(6), board_id));
+		let sorted = |pairs: Vec<(u64, u64)>| -> Vec<(u64, u64)> {
+			pairs.into_iter().map(|(a, b)| (a.min(b), a.max(b))).collect()
+		};
+		assert_ok!(ConnectFour::start_swiss_round(Origin::signed(7), tournament_id));
+		assert_eq!(sorted(swiss_pairings(tournament_id)), vec![(1, 4), (2, 5), (3, 6)]);
+		// 1 and 6 win, 2 and 5 draw.
+		assert_ok!(ConnectFour::resign(Origin::signed(4), swiss_board(tournament_id, 1)));
+		assert_ok!(ConnectFour::resign(Origin::signed(3), swiss_board(tournament_id, 6)));
+		let board_id = swiss_board(tournament_id, 5);
+		assert_ok!(ConnectFour::offer_draw(Origin::signed(5), board_id));
+		assert_ok!(ConnectFour::accept_draw(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::start_swiss_round(Origin::signed(7), tournament_id));
+		assert_eq!(sorted(swiss_pairings(tournament_id)), vec![(1, 6), (4, 5), (2, 3)]);
+		for player in [1, 4, 2] {
+			let board_id = swiss_board(tournament_id, player);
+			let opponent = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+			let opponent = if opponent.player_1 == player { opponent.player_2 } else { opponent.player_1 };
+			assert_ok!(ConnectFour::offer_draw(Origin::signed(player), board_id));
+			assert_ok!(ConnectFour::accept_draw(Origin::signed(opponent), board_id));
+		}
+		assert_ok!(ConnectFour::start_swiss_round(Origin::signed(7), tournament_id));
+		// 1, 3 and 5 have met 2, 4 and 6, so one game has to be a rematch.
+		assert_eq!(sorted(swiss_pairings(tournament_id)), vec![(1, 5), (4, 6), (2, 3)]);
+	})
+}
+
+/// Starts a game between accounts 1 and 2, returning its board.
+fn start_game_between_1_and_2() -> H256 {
+	assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+	let board_id = board_between(1, 2);
+	assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+	board_id
+}
+
+/// The bets on the result of a game share the whole pot, in proportion to their amounts.
+#[test]
+fn bets_on_the_result_share_the_pot() {
+	new_test_ext().execute_with(|| {
+		let board_id = start_game_between_1_and_2();
+		assert_ok!(ConnectFour::place_bet(Origin::signed(3), board_id, WinState::Player(Player::One), 100));
+		assert_ok!(ConnectFour::place_bet(Origin::signed(4), board_id, WinState::Player(Player::One), 300));
+		assert_ok!(ConnectFour::place_bet(Origin::signed(5), board_id, WinState::Draw, 200));
+		assert_ok!(ConnectFour::place_bet(Origin::signed(6), board_id, WinState::Player(Player::Two), 200));
+		System::assert_last_event(crate::Event::<Test>::BetPlaced(6, board_id, WinState::Player(Player::Two), 200).into());
+		assert_eq!(ConnectFour::get_bets(board_id).len(), 4);
+		assert_eq!(Balances::reserved_balance(4), 300);
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		System::assert_has_event(crate::Event::<Test>::BetsSettled(board_id, 800).into());
+		assert_eq!(Balances::free_balance(3), INITIAL_BALANCE + 100);
+		assert_eq!(Balances::free_balance(4), INITIAL_BALANCE + 300);
+		assert_eq!(Balances::free_balance(5), INITIAL_BALANCE - 200);
+		assert_eq!(Balances::free_balance(6), INITIAL_BALANCE - 200);
+		for bettor in 3..=6 {
+			assert_eq!(Balances::reserved_balance(bettor), 0);
+		}
+		assert_eq!(ConnectFour::get_bets(board_id).len(), 0);
+	})
+}
+
+/// The bets on the result get their share of the pot rounded down, the dust goes to the
+/// house, and no funds are created or lost.
+#[test]
+fn bets_pay_out_the_whole_pot() {
+	new_test_ext().execute_with(|| {
+		let issuance = Balances::total_issuance();
+		let board_id = start_game_between_1_and_2();
+		assert_ok!(ConnectFour::place_bet(Origin::signed(3), board_id, WinState::Player(Player::One), 100));
+		assert_ok!(ConnectFour::place_bet(Origin::signed(4), board_id, WinState::Player(Player::One), 200));
+		assert_ok!(ConnectFour::place_bet(Origin::signed(5), board_id, WinState::Draw, 50));
+		assert_ok!(ConnectFour::place_bet(Origin::signed(6), board_id, WinState::Player(Player::Two), 50));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		System::assert_has_event(crate::Event::<Test>::BetsSettled(board_id, 400).into());
+		assert_eq!(Balances::free_balance(3), INITIAL_BALANCE + 33);
+		assert_eq!(Balances::free_balance(4), INITIAL_BALANCE + 66);
+		assert_eq!(Balances::free_balance(HOUSE), HOUSE_BALANCE + 1);
+		for bettor in [5, 6] {
+			assert_eq!(Balances::free_balance(bettor), INITIAL_BALANCE - 50);
+		}
+		for bettor in 3..=6 {
+			assert_eq!(Balances::reserved_balance(bettor), 0);
+		}
+		assert_eq!(Balances::total_issuance(), issuance);
+	})
+}
+
+/// When nobody bet on the result of a game, every bet is refunded.
+#[test]
+fn bets_are_refunded_when_nobody_bet_on_the_result() {
+	new_test_ext().execute_with(|| {
+		let board_id = start_game_between_1_and_2();
+		assert_ok!(ConnectFour::place_bet(Origin::signed(3), board_id, WinState::Draw, 100));
+		assert_ok!(ConnectFour::place_bet(Origin::signed(4), board_id, WinState::Player(Player::Two), 100));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		System::assert_has_event(crate::Event::<Test>::BetsRefunded(board_id).into());
+		for bettor in [3, 4] {
+			assert_eq!(Balances::free_balance(bettor), INITIAL_BALANCE);
+			assert_eq!(Balances::reserved_balance(bettor), 0);
+		}
+	})
+}
+
+/// Only others can bet on an active game, on one of its results, before the cutoff.
+#[test]
+fn cannot_place_invalid_bets() {
+	new_test_ext().execute_with(|| {
+		let win = WinState::Player(Player::One);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_noop!(ConnectFour::place_bet(Origin::signed(3), board_id, win, 100), Error::<Test>::ChallengeNotYetAccepted);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_noop!(ConnectFour::place_bet(Origin::signed(1), board_id, win, 100), Error::<Test>::CannotBetOnYourOwnGame);
+		assert_noop!(ConnectFour::place_bet(Origin::signed(3), board_id, WinState::Ongoing, 100), Error::<Test>::InvalidBetOutcome);
+		assert_noop!(ConnectFour::place_bet(Origin::signed(3), board_id, win, MinimumBet::get() - 1), Error::<Test>::BetTooSmall);
+		assert_noop!(ConnectFour::place_bet(Origin::signed(3), board_id, win, INITIAL_BALANCE * 2), Error::<Test>::CannotAffordBet);
+		for bettor in 3..3 + MaxBetsPerGame::get() as u64 {
+			assert_ok!(ConnectFour::place_bet(Origin::signed(bettor), board_id, win, 100));
+		}
+		assert_noop!(ConnectFour::place_bet(Origin::signed(3), board_id, win, 100), Error::<Test>::TooManyBets);
+		let board_id = start_game_between_1_and_2();
+		for column in 0..BettingCutoff::get() as u8 {
+			let player = if column % 2 == 0 { 1 } else { 2 };
+			assert_ok!(ConnectFour::play(Origin::signed(player), board_id, column));
+		}
+		assert_noop!(ConnectFour::place_bet(Origin::signed(3), board_id, win, 100), Error::<Test>::BettingClosed);
+		assert_ok!(ConnectFour::resign(Origin::signed(1), board_id));
+		assert_noop!(ConnectFour::place_bet(Origin::signed(3), board_id, win, 100), Error::<Test>::GameEnded);
+	})
+}
+
+/// Taking moves back doesn't reopen betting once the cutoff has been reached.
+#[test]
+fn betting_stays_closed_after_a_takeback() {
+	new_test_ext().execute_with(|| {
+		let win = WinState::Player(Player::One);
+		let settings = GameSettings { rated: false, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::place_bet(Origin::signed(3), board_id, win, 100));
+		for column in 0..BettingCutoff::get() as u8 {
+			let player = if column % 2 == 0 { 1 } else { 2 };
+			assert_ok!(ConnectFour::play(Origin::signed(player), board_id, column));
+		}
+		assert_ok!(ConnectFour::request_takeback(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::accept_takeback(Origin::signed(1), board_id));
+		assert_eq!(ConnectFour::get_move_history(board_id).len() as u32, BettingCutoff::get() - 1);
+		assert_noop!(ConnectFour::place_bet(Origin::signed(4), board_id, win, 100), Error::<Test>::BettingClosed);
+	})
+}
+
+/// Every move of a game is recorded, in order.
+#[test]
+fn moves_are_recorded() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 3));
+		run_to_block(2);
+		assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 4));
+		let moves = ConnectFour::get_move_history(board_id);
+		assert_eq!(
+			moves.into_inner(),
+			vec![
+				Move { player: Player::One, column: 3, block: 1, kind: MoveKind::Drop, clock: None },
+				Move { player: Player::Two, column: 4, block: 2, kind: MoveKind::Drop, clock: None },
+			]
+		);
+	})
+}
+
+/// Replaying the moves of a finished game gives the same result.
+#[test]
+fn can_replay_a_game() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		for _ in 0..3 {
+			assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+			assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+		}
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		let moves = ConnectFour::get_move_history(board_id);
+		assert_eq!(moves.len(), 7);
+		let replayed = ConnectFour::replay(board.settings, &moves).unwrap();
+		assert_eq!(replayed.win_state, board.win_state);
+		assert_eq!(replayed.state, board.state);
+		assert_eq!(replayed.winning_line, board.winning_line);
+		assert_eq!(replayed.winning_line.unwrap().direction, Direction::Vertical);
+		// a replay of part of the game is still ongoing.
+		let replayed = ConnectFour::replay(board.settings, &moves[..6]).unwrap();
+		assert_eq!(replayed.win_state, WinState::Ongoing);
+		assert_eq!(replayed.player_to_move(), Player::One);
+	})
+}
+
+/// Replaying moves that could not have been played fails.
+#[test]
+fn cannot_replay_invalid_moves() {
+	new_test_ext().execute_with(|| {
+		let first_move = Move { player: Player::One, column: 0, block: 1, kind: MoveKind::Drop, clock: None };
+		let moves = vec![first_move, first_move];
+		assert_eq!(ConnectFour::replay(GameSettings::default(), &moves).unwrap_err(), GameError::NotYourMove);
+		let pop = Move { player: Player::One, column: 0, block: 1, kind: MoveKind::Pop, clock: None };
+		assert_eq!(ConnectFour::replay(GameSettings::default(), &[pop]).unwrap_err(), GameError::PopNotAllowed);
+	})
+}
+
+/// A PopOut game that reaches the maximum number of moves is drawn.
+#[test]
+fn pop_out_game_is_drawn_after_max_moves() {
+	new_test_ext().execute_with(|| {
+		let settings = GameSettings { variant: GameVariant::PopOut, ..Default::default() };
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, settings, None));
+		let board_id = board_between(1, 2);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), board_id));
+		// both players keep dropping and popping the same coins.
+		for _ in 0..MaxMoves::get() / 4 {
+			assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+			assert_ok!(ConnectFour::play(Origin::signed(2), board_id, 1));
+			assert_ok!(ConnectFour::pop(Origin::signed(1), board_id, 0));
+			assert_ok!(ConnectFour::pop(Origin::signed(2), board_id, 1));
+		}
+		let board = ConnectFour::get_connect_four_board_by_id(board_id).unwrap();
+		assert_eq!(board.win_state, WinState::Draw);
+		assert_eq!(<AccountScoreCard<Test>>::get(1).draw, 1);
+		let moves = ConnectFour::get_move_history(board_id);
+		assert_eq!(moves.len() as u32, MaxMoves::get());
+		assert_eq!(ConnectFour::replay(settings, &moves).unwrap().win_state, WinState::Draw);
+	})
+}
+
+/// A grid and its bitboards convert back and forth.
+#[test]
+fn check_bitboards() {
+	let grid = vec![
+		vec![Coin::Player1, Coin::Player2, Coin::Player1],
+		vec![Coin::Player2, Coin::Empty, Coin::Player1],
+		vec![Coin::Empty, Coin::Empty, Coin::Player2],
+	];
+	let bitboards = Bitboards::from_grid(&grid);
+	// every column takes 4 bits, the top one is always empty.
+	assert_eq!(bitboards.player_1, 0b0011_0000_0001);
+	assert_eq!(bitboards.player_2, 0b0100_0001_0010);
+	assert_eq!(bitboards.heights, vec![2, 1, 3]);
+	assert_eq!(bitboards.coin_at(3, 1, 2), Coin::Player1);
+	assert_eq!(bitboards.to_grid(3), grid);
+}
+
+/// Stores the boards of the first release, and the list of all boards, like it did.
+fn put_v0_boards(boards: &[(Option<Vec<Vec<Coin>>>, u64, u64, bool, H256, bool, Option<u64>, WinState)]) {
+	use frame_support::traits::{PalletInfoAccess, StorageVersion};
+	StorageVersion::new(0).put::<ConnectFour>();
+	for board in boards {
+		frame_support::storage::unhashed::put(&<ConnectFourBoardById<Test>>::hashed_key_for(board.4), board);
+	}
+	let board_ids: Vec<H256> = boards.iter().map(|board| board.4).collect();
+	let list_key = frame_support::storage::storage_prefix(ConnectFour::name().as_bytes(), b"ConnectFourBoards");
+	frame_support::storage::unhashed::put(&list_key, &board_ids);
+}
+
+/// The v1 migration brings the boards of the first release to the current layout, in the
+/// blocks after the upgrade, and indexes them in the order they were created.
+#[test]
+fn migrates_boards_from_v0() {
+	new_test_ext().execute_with(|| {
+		use frame_support::traits::{GetStorageVersion, Hooks};
+		let (ongoing, pending, finished) = (H256::repeat_byte(1), H256::repeat_byte(2), H256::repeat_byte(3));
+		let mut grid = vec![vec![Coin::Empty; 7]; 6];
+		grid[0][3] = Coin::Player1;
+		grid[1][3] = Coin::Player2;
+		// the fields of a board before v1, in order.
+		put_v0_boards(&[
+			(Some(grid.clone()), 1, 2, true, ongoing, true, Some(2), WinState::Ongoing),
+			(None, 3, 4, false, pending, false, None, WinState::Ongoing),
+			(None, 5, 6, true, finished, false, Some(5), WinState::Player(Player::One)),
+		]);
+		for (player, other_player, board_id) in [(1u64, 2u64, ongoing), (2, 1, ongoing), (3, 4, pending), (4, 3, pending)] {
+			frame_support::storage::unhashed::put(&<Challenges<Test>>::hashed_key_for(player, other_player), &board_id);
+		}
+		ConnectFour::on_runtime_upgrade();
+		assert_eq!(ConnectFour::on_chain_storage_version(), 1);
+		run_to_block(2);
+		assert!(!crate::migrations::v1::migrating::<Test>());
+		let board = ConnectFour::get_connect_four_board_by_id(ongoing).unwrap();
+		assert_eq!(board.grid(), Some(grid));
+		assert_eq!(board.last_move_at, 2);
+		assert_eq!(board.settings, GameSettings::default());
+		assert_eq!(ConnectFour::get_challenges(1, 2), vec![ongoing]);
+		assert_eq!(ConnectFour::get_challenges(4, 3), vec![pending]);
+		let board = ConnectFour::get_connect_four_board_by_id(finished).unwrap();
+		assert_eq!(board.win_state, WinState::Player(Player::One));
+		assert_eq!(board.winning_line, None);
+		assert_eq!(<crate::PruneQueue<Test>>::get(2 + PruneAfter::get()), vec![finished]);
+		assert_eq!(<ConnectFourBoards<Test>>::count(), 3);
+		assert_eq!(ConnectFour::board_ids(0, 3), vec![ongoing, pending, finished]);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(4), pending));
+		// player 1 hasn't moved in time.
+		assert_eq!(<MoveDeadlines<Test>>::get(2 + MoveTimeout::get()), vec![ongoing, pending]);
+		run_to_block(2 + MoveTimeout::get());
+		let board = ConnectFour::get_connect_four_board_by_id(ongoing).unwrap();
+		assert_eq!(board.win_state, WinState::Player(Player::Two));
+	})
+}
+
+/// The boards of the first release are migrated as far as the weight limit allows, and the
+/// calls of the pallet fail until all of them are.
+#[test]
+fn calls_fail_until_all_boards_are_migrated() {
+	new_test_ext().execute_with(|| {
+		use frame_support::traits::{Get, Hooks};
+		let (first, second) = (H256::repeat_byte(1), H256::repeat_byte(2));
+		put_v0_boards(&[
+			(None, 1, 2, false, first, false, None, WinState::Ongoing),
+			(None, 3, 4, false, second, false, None, WinState::Ongoing),
+		]);
+		ConnectFour::on_runtime_upgrade();
+		assert!(crate::migrations::v1::migrating::<Test>());
+		assert_noop!(
+			ConnectFour::challenge_player(Origin::signed(5), 6, GameSettings::default(), None),
+			Error::<Test>::MigrationInProgress
+		);
+		let db_weight = <Test as frame_system::Config>::DbWeight::get();
+		// the list, and a board queued in up to 16 blocks.
+		let one_board = db_weight.reads_writes(22, 10);
+		assert_eq!(crate::migrations::v1::migrate_boards::<Test>(one_board), one_board);
+		assert!(ConnectFour::get_connect_four_board_by_id(first).is_some());
+		assert_eq!(ConnectFour::board_ids(0, 2), vec![first]);
+		assert_noop!(ConnectFour::accept_challenge(Origin::signed(2), first), Error::<Test>::MigrationInProgress);
+		run_to_block(2);
+		assert!(!crate::migrations::v1::migrating::<Test>());
+		assert_eq!(ConnectFour::board_ids(0, 2), vec![first, second]);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(2), first));
+	})
+}
+
+/// Finished boards are removed `PruneAfter` blocks after they ended, when there is weight
+/// left in a block, and the deposit is paid back to the challenger.
+#[test]
+fn finished_games_are_pruned_on_idle() {
+	new_test_ext().execute_with(|| {
+		use frame_support::{traits::Hooks, weights::Weight};
+		GameDeposit::set(5);
+		let board_id = start_game_between_1_and_2();
+		assert_eq!(Balances::reserved_balance(1), 5);
+		assert_ok!(ConnectFour::play(Origin::signed(1), board_id, 0));
+		assert_ok!(ConnectFour::resign(Origin::signed(2), board_id));
+		ConnectFour::on_idle(PruneAfter::get(), Weight::MAX);
+		assert!(ConnectFour::get_connect_four_board_by_id(board_id).is_some());
+		ConnectFour::on_idle(1 + PruneAfter::get(), Weight::MAX);
+		System::assert_last_event(crate::Event::<Test>::GamePruned(board_id).into());
+		assert!(ConnectFour::get_connect_four_board_by_id(board_id).is_none());
+		assert_eq!(ConnectFour::get_move_history(board_id).len(), 0);
+		assert_eq!(<ConnectFourBoards<Test>>::count(), 0);
+		assert_eq!(ConnectFour::get_board_by_index(0), None);
+		assert_eq!(Balances::reserved_balance(1), 0);
+	})
+}
+
+/// Pruning stops when the weight of the next board doesn't fit, and goes on in a later
+/// block.
+#[test]
+fn pruning_stays_within_the_weight_limit() {
+	new_test_ext().execute_with(|| {
+		use frame_support::{traits::{Get, Hooks}, weights::Weight};
+		let first = start_game_between_1_and_2();
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(3), 4, GameSettings::default(), None));
+		let second = board_between(3, 4);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(4), second));
+		assert_ok!(ConnectFour::resign(Origin::signed(1), first));
+		assert_ok!(ConnectFour::resign(Origin::signed(3), second));
+		let db_weight = <Test as frame_system::Config>::DbWeight::get();
+		let cursor_only = db_weight.reads_writes(1, 1);
+		assert_eq!(ConnectFour::on_idle(1 + PruneAfter::get(), cursor_only), cursor_only);
+		assert_eq!(<ConnectFourBoards<Test>>::count(), 2);
+		let one_board = db_weight.reads_writes(6, 9);
+		assert_eq!(ConnectFour::on_idle(1 + PruneAfter::get(), one_board), one_board);
+		assert_eq!(<ConnectFourBoards<Test>>::count(), 1);
+		ConnectFour::on_idle(2 + PruneAfter::get(), Weight::MAX);
+		assert_eq!(<ConnectFourBoards<Test>>::count(), 0);
+		assert!(ConnectFour::get_connect_four_board_by_id(first).is_none());
+		assert!(ConnectFour::get_connect_four_board_by_id(second).is_none());
+	})
+}
+
+/// Anyone can reap a game that ended at least `PruneAfter` blocks ago.
+#[test]
+fn can_reap_old_finished_games() {
+	new_test_ext().execute_with(|| {
+		use frame_support::{traits::Hooks, weights::Weight};
+		GameDeposit::set(5);
+		let board_id = start_game_between_1_and_2();
+		assert_noop!(ConnectFour::reap_game(Origin::signed(7), board_id), Error::<Test>::GameNotFinished);
+		run_to_block(3);
+		assert_ok!(ConnectFour::resign(Origin::signed(1), board_id));
+		run_to_block(2 + PruneAfter::get());
+		assert_noop!(ConnectFour::reap_game(Origin::signed(7), board_id), Error::<Test>::GameTooRecent);
+		run_to_block(3 + PruneAfter::get());
+		assert_ok!(ConnectFour::reap_game(Origin::signed(7), board_id));
+		System::assert_last_event(crate::Event::<Test>::GamePruned(board_id).into());
+		assert!(ConnectFour::get_connect_four_board_by_id(board_id).is_none());
+		assert_eq!(Balances::reserved_balance(1), 0);
+		assert_noop!(ConnectFour::reap_game(Origin::signed(7), board_id), Error::<Test>::GameDoesNotExist);
+		// the queued board has already been removed.
+		ConnectFour::on_idle(3 + PruneAfter::get(), Weight::MAX);
+		assert_eq!(Balances::reserved_balance(1), 0);
+	})
+}
+
+/// The deposit of a challenge is reserved from the challenger, and paid back when the
+/// challenge is declined.
+#[test]
+fn challenge_deposit_is_paid_back_when_declined() {
+	new_test_ext().execute_with(|| {
+		GameDeposit::set(5);
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None));
+		let board_id = board_between(1, 2);
+		assert_eq!(ConnectFour::get_deposit(board_id), Some((1, 5)));
+		assert_ok!(ConnectFour::decline_challenge(Origin::signed(2), board_id));
+		assert_eq!(ConnectFour::get_deposit(board_id), None);
+		assert_eq!(Balances::reserved_balance(1), 0);
+		GameDeposit::set(INITIAL_BALANCE + 1);
+		assert_noop!(
+			ConnectFour::challenge_player(Origin::signed(1), 2, GameSettings::default(), None),
+			Error::<Test>::CannotAffordDeposit
+		);
+	})
+}
+
+/// A board queued for a block whose queue is full is queued for the next block with room.
+#[test]
+fn full_queues_spill_over_to_the_next_block() {
+	new_test_ext().execute_with(|| {
+		MaxQueuedPerBlock::set(1);
+		let first = start_game_between_1_and_2();
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(3), 4, GameSettings::default(), None));
+		let second = board_between(3, 4);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(4), second));
+		let deadline = 1 + MoveTimeout::get();
+		assert_eq!(<MoveDeadlines<Test>>::get(deadline), vec![first]);
+		assert_eq!(<MoveDeadlines<Test>>::get(deadline + 1), vec![second]);
+		run_to_block(deadline);
+		assert_eq!(ConnectFour::get_connect_four_board_by_id(first).unwrap().active, false);
+		assert_eq!(ConnectFour::get_connect_four_board_by_id(second).unwrap().active, true);
+		// the player to move loses a block late.
+		run_to_block(deadline + 1);
+		assert_eq!(ConnectFour::get_connect_four_board_by_id(second).unwrap().active, false);
+	})
+}
+
+/// An entry is only queued for one of the 16 blocks from the block it is due, and the call
+/// that queues it fails when all of their queues are full.
+#[test]
+fn queueing_fails_when_the_next_queues_are_full() {
+	new_test_ext().execute_with(|| {
+		MaxQueuedPerBlock::set(1);
+		for creator in 1..=16 {
+			assert_ok!(ConnectFour::create_open_challenge(Origin::signed(creator), GameSettings::default()));
+		}
+		assert_noop!(
+			ConnectFour::create_open_challenge(Origin::signed(17), GameSettings::default()),
+			Error::<Test>::QueuesFull
+		);
+	})
+}
+
+/// Move deadlines are checked as far as the weight limit allows, and the rest are checked
+/// in a later block.
+#[test]
+fn move_deadlines_stay_within_the_weight_limit() {
+	new_test_ext().execute_with(|| {
+		use frame_support::traits::Get;
+		let first = start_game_between_1_and_2();
+		assert_ok!(ConnectFour::challenge_player(Origin::signed(3), 4, GameSettings::default(), None));
+		let second = board_between(3, 4);
+		assert_ok!(ConnectFour::accept_challenge(Origin::signed(4), second));
+		let deadline = 1 + MoveTimeout::get();
+		run_to_block(deadline - 1);
+		let db_weight = <Test as frame_system::Config>::DbWeight::get();
+		let one_game = db_weight.reads_writes(5, 7) + ConnectFour::game_end_weight();
+		assert_eq!(ConnectFour::expire_moves(deadline, one_game), one_game);
+		let ended = [first, second]
+			.iter()
+			.filter(|board_id| !ConnectFour::get_connect_four_board_by_id(board_id).unwrap().active)
+			.count();
+		assert_eq!(ended, 1);
+		run_to_block(deadline + 1);
+		assert_eq!(ConnectFour::get_connect_four_board_by_id(first).unwrap().active, false);
+		assert_eq!(ConnectFour::get_connect_four_board_by_id(second).unwrap().active, false);
+	})
+}
+
+/// The mock configuration passes the integrity checks of the pallet.
+#[test]
+fn mock_config_passes_integrity_test() {
+	use frame_support::traits::Hooks;
+	<ConnectFour as Hooks<u64>>::integrity_test();
+}
+
@@ -14,7 +14,10 @@ use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
-	traits::{AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, Verify},
+	traits::{
+		AccountIdConversion, AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount,
+		NumberFor, Verify,
+	},
 	transaction_validity::{TransactionSource, TransactionValidity},
 	ApplyExtrinsicResult, MultiSignature,
 };
@@ -33,7 +36,7 @@ pub use frame_support::{
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
 		IdentityFee, Weight,
 	},
-	StorageValue,
+	PalletId, StorageValue,
 };
 pub use frame_system::Call as SystemCall;
 pub use pallet_balances::Call as BalancesCall;
@@ -270,7 +273,27 @@ parameter_types! {
 	pub const PointsForWin: u32 = 5;
 	pub const PointsForLoss: u32 = 2;
 	pub const PointsForDraw: u32 = 3;
-
+	pub const MoveTimeout: BlockNumber = 10 * MINUTES;
+	pub const OpenChallengeExpiry: BlockNumber = HOURS;
+	pub const MaxRows: u32 = 8;
+	pub const MaxColumns: u32 = 9;
+	pub const InitialRating: u32 = 1500;
+	pub const RatingKFactor: u32 = 32;
+	pub const MaxGamesPerPair: u32 = 2;
+	pub const MaxMoves: u32 = 100;
+	pub const TakebacksInRatedGames: bool = false;
+	pub const HouseFee: Perbill = Perbill::zero();
+	pub const ConnectFourPalletId: PalletId = PalletId(*b"py/cnct4");
+	pub HouseAccount: AccountId = ConnectFourPalletId::get().into_account_truncating();
+	pub const MaxTournamentPlayers: u32 = 64;
+	pub const MaxLeaguePlayers: u32 = 10;
+	pub const LeagueSeasonLength: BlockNumber = 7 * DAYS;
+	pub const BettingCutoff: u32 = 10;
+	pub const MaxBetsPerGame: u32 = 100;
+	pub const MinimumBet: Balance = 100 * EXISTENTIAL_DEPOSIT;
+	pub const PruneAfter: BlockNumber = 30 * DAYS;
+	pub const GameDeposit: Balance = 10 * EXISTENTIAL_DEPOSIT;
+	pub const MaxQueuedPerBlock: u32 = 256;
 }
 impl pallet_connect_four::Config for Runtime {
 	type Event = Event;
@@ -278,6 +301,27 @@ impl pallet_connect_four::Config for Runtime {
 	type PointsForWin = PointsForWin;
 	type PointsForLoss = PointsForLoss;
 	type PointsForDraw = PointsForDraw;
+	type MoveTimeout = MoveTimeout;
+	type OpenChallengeExpiry = OpenChallengeExpiry;
+	type MaxRows = MaxRows;
+	type MaxColumns = MaxColumns;
+	type InitialRating = InitialRating;
+	type RatingKFactor = RatingKFactor;
+	type MaxGamesPerPair = MaxGamesPerPair;
+	type MaxMoves = MaxMoves;
+	type TakebacksInRatedGames = TakebacksInRatedGames;
+	type Currency = Balances;
+	type HouseFee = HouseFee;
+	type HouseAccount = HouseAccount;
+	type MaxTournamentPlayers = MaxTournamentPlayers;
+	type MaxLeaguePlayers = MaxLeaguePlayers;
+	type LeagueSeasonLength = LeagueSeasonLength;
+	type BettingCutoff = BettingCutoff;
+	type MaxBetsPerGame = MaxBetsPerGame;
+	type MinimumBet = MinimumBet;
+	type PruneAfter = PruneAfter;
+	type GameDeposit = GameDeposit;
+	type MaxQueuedPerBlock = MaxQueuedPerBlock;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.